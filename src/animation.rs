@@ -0,0 +1,285 @@
+use std::io::Write;
+
+use gif::{Encoder, EncodingError, Frame, Repeat};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    base::BaseArt,
+    bonsai::{
+        utility::{message_style, Style},
+        Val,
+    },
+    font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH},
+    palette::Palette,
+    render::Grid,
+    style::{Attribute, Color},
+    Error, Result,
+};
+
+/// Settings for an animated GIF of a growing tree
+pub struct GifOptions<'a> {
+    /// Width of the canvas in cells
+    pub width: u16,
+    /// Height of the canvas in cells
+    pub height: u16,
+    pub base: Option<&'a BaseArt>,
+    pub message: Option<&'a str>,
+    /// Seconds between each step of growth, like `--time` in live mode
+    pub time: f64,
+    /// Seconds to show the finished tree before the animation starts over
+    pub wait: f64,
+    /// Size of each font pixel in image pixels
+    pub scale: u16,
+    pub palette: &'a Palette,
+}
+
+/// The smallest frame delay, in hundredths of a second, that viewers reliably
+/// honor
+const MIN_DELAY: f64 = 2.0;
+
+/// Write the growth of `tree` as an animated GIF, drawing one step per frame
+/// the same way live mode does
+pub fn write_gif<W: Write>(out: W, tree: &[Val], options: &GifOptions) -> Result<()> {
+    let scale = options.scale.max(1);
+    let size = (GLYPH_WIDTH as u16)
+        .checked_mul(scale)
+        .zip((GLYPH_HEIGHT as u16).checked_mul(scale))
+        .and_then(|(cell_width, cell_height)| {
+            Some((
+                options.width.checked_mul(cell_width)?,
+                options.height.checked_mul(cell_height)?,
+            ))
+        });
+    let Some((image_width, image_height)) = size else {
+        return Err(Error::InvalidConfig(format!(
+            "a {}x{} GIF at scale {} is too large",
+            options.width, options.height, scale
+        )));
+    };
+
+    encode(out, tree, options, (image_width, image_height)).map_err(|err| match err {
+        EncodingError::Io(err) => Error::Io(err),
+        EncodingError::Format(err) => Error::Encoding(err.to_string()),
+    })
+}
+
+fn encode<W: Write>(
+    out: W,
+    tree: &[Val],
+    options: &GifOptions,
+    (image_width, image_height): (u16, u16),
+) -> Result<(), EncodingError> {
+    let colors = ColorTable::new(tree, options);
+    let scale = options.scale.max(1);
+
+    let mut encoder = Encoder::new(out, image_width, image_height, &colors.flat())?;
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    // group steps that are quicker than a viewer can show into one frame
+    let step = options.time.max(0.0) * 100.0;
+    let steps_per_frame = if step >= MIN_DELAY {
+        1
+    } else if step > 0.0 {
+        (MIN_DELAY / step).ceil() as usize
+    } else {
+        tree.len().max(1)
+    };
+    let delay = (step * steps_per_frame as f64).round().max(MIN_DELAY) as u16;
+
+    let mut grid = Grid::new(options.width, options.height);
+    if let Some(base) = options.base {
+        grid.draw_base(base);
+    }
+    let mut frames = FrameWriter::new(&mut encoder);
+    let full = (0, 0, options.width, options.height);
+    frames.push(render_frame(&grid, full, &colors, scale, delay))?;
+
+    for steps in tree.chunks(steps_per_frame) {
+        let mut changed: Option<(u16, u16, u16, u16)> = None;
+        for val in steps {
+            grid.put_str(val.pos.x, val.pos.y, &val.char, &val.style);
+            if let Some(area) = clip(val, &grid) {
+                changed = Some(match changed {
+                    Some(previous) => union(previous, area),
+                    None => area,
+                });
+            }
+        }
+        if let Some(area) = changed {
+            frames.push(render_frame(&grid, area, &colors, scale, delay))?;
+        }
+    }
+
+    if let Some(message) = options.message {
+        grid.draw_message(message);
+        frames.push(render_frame(&grid, full, &colors, scale, delay))?;
+    }
+
+    let hold = (options.wait.max(0.0) * 100.0).round() as u16;
+    frames.finish(hold)
+}
+
+/// Writes frames one behind, so the final hold can be added to the last one
+struct FrameWriter<'a, W: Write> {
+    encoder: &'a mut Encoder<W>,
+    pending: Option<Frame<'static>>,
+}
+
+impl<'a, W: Write> FrameWriter<'a, W> {
+    fn new(encoder: &'a mut Encoder<W>) -> Self {
+        FrameWriter {
+            encoder,
+            pending: None,
+        }
+    }
+
+    fn push(&mut self, frame: Frame<'static>) -> Result<(), EncodingError> {
+        if let Some(previous) = self.pending.replace(frame) {
+            self.encoder.write_frame(&previous)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, hold: u16) -> Result<(), EncodingError> {
+        if let Some(mut last) = self.pending.take() {
+            last.delay = last.delay.saturating_add(hold);
+            self.encoder.write_frame(&last)?;
+        }
+        Ok(())
+    }
+}
+
+/// The cells covered by a step, as `(x, y, width, height)`, or `None` if it
+/// is entirely off the canvas
+fn clip(val: &Val, grid: &Grid) -> Option<(u16, u16, u16, u16)> {
+    let start = val.pos.x.max(0);
+    let end = (val.pos.x + val.char.width() as i32).min(grid.width() as i32);
+    if val.pos.y < 0 || val.pos.y >= grid.height() as i32 || start >= end {
+        return None;
+    }
+    Some((start as u16, val.pos.y as u16, (end - start) as u16, 1))
+}
+
+fn union(a: (u16, u16, u16, u16), b: (u16, u16, u16, u16)) -> (u16, u16, u16, u16) {
+    let x = a.0.min(b.0);
+    let y = a.1.min(b.1);
+    let right = (a.0 + a.2).max(b.0 + b.2);
+    let bottom = (a.1 + a.3).max(b.1 + b.3);
+    (x, y, right - x, bottom - y)
+}
+
+/// Rasterize the cells in `area` of the grid into a frame placed at the
+/// matching spot of the image
+fn render_frame(
+    grid: &Grid,
+    (x, y, width, height): (u16, u16, u16, u16),
+    colors: &ColorTable,
+    scale: u16,
+    delay: u16,
+) -> Frame<'static> {
+    let scale = scale as usize;
+    let pixel_width = width as usize * GLYPH_WIDTH * scale;
+    let pixel_height = height as usize * GLYPH_HEIGHT * scale;
+    let mut buffer = vec![0; pixel_width * pixel_height];
+
+    for row in 0..height {
+        for col in 0..width {
+            let Some(cell) = grid.cell(x + col, y + row) else {
+                continue;
+            };
+            let background = colors.index(colors.palette_background(&cell.style));
+            let foreground = colors.index(colors.palette.rgb(cell.style.foreground_color));
+            let bold = cell.style.attribute == Attribute::Bold;
+            let rows = cell.symbol.chars().next().map(glyph);
+
+            for glyph_y in 0..GLYPH_HEIGHT {
+                let bits = rows.map_or(0, |rows| rows[glyph_y]);
+                for glyph_x in 0..GLYPH_WIDTH {
+                    let lit = |gx: usize| bits & (1 << (GLYPH_WIDTH - 1 - gx)) != 0;
+                    // bold text is drawn a pixel wider
+                    let on = lit(glyph_x) || (bold && glyph_x > 0 && lit(glyph_x - 1));
+                    let index = if on { foreground } else { background };
+
+                    let px = (col as usize * GLYPH_WIDTH + glyph_x) * scale;
+                    let py = (row as usize * GLYPH_HEIGHT + glyph_y) * scale;
+                    for sy in 0..scale {
+                        let start = (py + sy) * pixel_width + px;
+                        buffer[start..start + scale].fill(index);
+                    }
+                }
+            }
+        }
+    }
+
+    Frame {
+        left: x * GLYPH_WIDTH as u16 * scale as u16,
+        top: y * GLYPH_HEIGHT as u16 * scale as u16,
+        width: pixel_width as u16,
+        height: pixel_height as u16,
+        buffer: buffer.into(),
+        delay,
+        ..Frame::default()
+    }
+}
+
+/// The global color table of the GIF, built from every style the animation
+/// can show
+struct ColorTable<'a> {
+    palette: &'a Palette,
+    colors: Vec<[u8; 3]>,
+}
+
+impl<'a> ColorTable<'a> {
+    fn new(tree: &[Val], options: &GifOptions<'a>) -> Self {
+        let mut table = ColorTable {
+            palette: options.palette,
+            colors: vec![options.palette.background_rgb()],
+        };
+
+        let mut styles: Vec<&Style> = tree.iter().map(|val| &val.style).collect();
+        if let Some(art) = options.base {
+            styles.extend(art.lines.iter().flatten().map(|span| &span.style));
+        }
+        let message = message_style();
+        styles.push(&message);
+        let plain = Style::default();
+        styles.push(&plain);
+
+        for style in styles {
+            table.add(table.palette.rgb(style.foreground_color));
+            table.add(table.palette_background(style));
+        }
+        table
+    }
+
+    fn palette_background(&self, style: &Style) -> [u8; 3] {
+        match style.background_color {
+            Color::Reset => self.palette.background_rgb(),
+            color => self.palette.rgb(color),
+        }
+    }
+
+    fn add(&mut self, rgb: [u8; 3]) {
+        if self.colors.len() < 256 && !self.colors.contains(&rgb) {
+            self.colors.push(rgb);
+        }
+    }
+
+    /// The index of a color, or of the closest color once the table is full
+    fn index(&self, rgb: [u8; 3]) -> u8 {
+        let distance = |c: &[u8; 3]| {
+            (0..3)
+                .map(|i| (c[i] as i32 - rgb[i] as i32).pow(2))
+                .sum::<i32>()
+        };
+        self.colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, c)| distance(c))
+            .map_or(0, |(i, _)| i as u8)
+    }
+
+    fn flat(&self) -> Vec<u8> {
+        self.colors.iter().flatten().copied().collect()
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    time::Duration,
+};
+
+use crossterm::{style, terminal};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    bonsai::utility::{wait_for_input, Input, Style},
+    render::Grid,
+    style::{Attribute, Color},
+    Error, Result,
+};
+
+/// Somewhere trees can be drawn. Drawing is done by writing crossterm
+/// commands, so a backend is a writer that knows its size and where input
+/// comes from
+pub trait Backend: Write {
+    /// The number of columns and rows to draw in
+    fn size(&self) -> Result<(u16, u16)>;
+
+    /// Wait for a key press or resize, giving up after `timeout`. Backends
+    /// without input return straight away
+    fn wait_for_input(&mut self, _timeout: Option<Duration>) -> Result<Option<Input>> {
+        Ok(None)
+    }
+}
+
+/// The terminal crossterm is attached to, drawn on through `out`, which is
+/// usually stdout
+pub struct CrosstermBackend<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(out: W) -> Self {
+        CrosstermBackend { out }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Write for CrosstermBackend<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn size(&self) -> Result<(u16, u16)> {
+        terminal::size().map_err(Error::Terminal)
+    }
+
+    fn wait_for_input(&mut self, timeout: Option<Duration>) -> Result<Option<Input>> {
+        wait_for_input(timeout)
+    }
+}
+
+/// Any writer, drawn on as if it were a terminal of a fixed size. It has no
+/// input, so live drawing doesn't wait between steps
+pub struct WriterBackend<W: Write> {
+    out: W,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> WriterBackend<W> {
+    pub fn new(out: W, width: u16, height: u16) -> Self {
+        WriterBackend { out, width, height }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Write for WriterBackend<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Backend for WriterBackend<W> {
+    fn size(&self) -> Result<(u16, u16)> {
+        Ok((self.width, self.height))
+    }
+}
+
+/// An in-memory terminal. Cursor moves, clears, colors and attributes
+/// written to it are interpreted onto a [`Grid`], so what would be on the
+/// screen can be checked without a terminal
+///
+/// Text running past the right edge is clipped instead of wrapping, and
+/// escape sequences it doesn't know are ignored
+#[derive(Clone, Debug)]
+pub struct VirtualScreen {
+    grid: Grid,
+    cursor: (i32, i32),
+    style: Style,
+    inputs: VecDeque<Input>,
+    /// Bytes of an escape sequence or character that was only partly
+    /// written
+    pending: Vec<u8>,
+}
+
+impl VirtualScreen {
+    pub fn new(width: u16, height: u16) -> Self {
+        VirtualScreen {
+            grid: Grid::new(width, height),
+            cursor: (0, 0),
+            style: Style::default(),
+            inputs: VecDeque::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// What is on the screen
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// The column and row the cursor is on
+    pub fn cursor(&self) -> (i32, i32) {
+        self.cursor
+    }
+
+    /// Queue input to be returned by [`Backend::wait_for_input`], one each
+    /// time it is called
+    pub fn send_input(&mut self, input: Input) {
+        self.inputs.push_back(input);
+    }
+
+    fn print(&mut self, text: &str) {
+        let (x, y) = self.cursor;
+        self.grid.put_str(x, y, text, &self.style);
+        self.cursor.0 += text.width() as i32;
+    }
+
+    /// Interpret the complete part of `pending`, leaving anything cut off
+    fn interpret(&mut self) {
+        let bytes = std::mem::take(&mut self.pending);
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => text.to_string(),
+            // a character cut off at the end is kept for the next write
+            Err(err) if err.error_len().is_none() => {
+                self.pending = bytes[err.valid_up_to()..].to_vec();
+                String::from_utf8_lossy(&bytes[..err.valid_up_to()]).into_owned()
+            }
+            Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let end = rest.find(['\x1b', '\r', '\n']).unwrap_or(rest.len());
+            if end > 0 {
+                self.print(&rest[..end]);
+                rest = &rest[end..];
+                continue;
+            }
+            match rest.as_bytes()[0] {
+                b'\r' => self.cursor.0 = 0,
+                b'\n' => self.cursor = (0, self.cursor.1 + 1),
+                _ => match escape_sequence(rest) {
+                    Some((sequence, length)) => {
+                        self.apply(sequence);
+                        rest = &rest[length..];
+                        continue;
+                    }
+                    None => {
+                        let mut pending = rest.as_bytes().to_vec();
+                        pending.append(&mut self.pending);
+                        self.pending = pending;
+                        return;
+                    }
+                },
+            }
+            rest = &rest[1..];
+        }
+    }
+
+    fn apply(&mut self, sequence: Escape) {
+        match sequence {
+            Escape::Csi { params, command } => {
+                let numbers: Vec<u16> = params
+                    .split(';')
+                    .map(|param| param.parse().unwrap_or(0))
+                    .collect();
+                let number = |i: usize| numbers.get(i).copied().unwrap_or(0);
+                match command {
+                    // the cursor position is one based
+                    'H' | 'f' => {
+                        self.cursor = (
+                            number(1).saturating_sub(1) as i32,
+                            number(0).saturating_sub(1) as i32,
+                        );
+                    }
+                    'J' if number(0) >= 2 => {
+                        self.grid = Grid::new(self.grid.width(), self.grid.height());
+                    }
+                    'm' if !params.starts_with('?') => self.set_graphics(params),
+                    _ => {}
+                }
+            }
+            Escape::Other => {}
+        }
+    }
+
+    /// Apply a Select Graphic Rendition sequence
+    fn set_graphics(&mut self, params: &str) {
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            let code: u8 = match param.parse() {
+                Ok(code) => code,
+                Err(_) if param.is_empty() => 0,
+                Err(_) => continue,
+            };
+            match code {
+                0 => self.style = Style::default(),
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some("5") => params.next().map(|index| format!("5;{}", index)),
+                        Some("2") => {
+                            let rgb: Vec<&str> = params.by_ref().take(3).collect();
+                            Some(format!("2;{}", rgb.join(";")))
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color.as_deref().and_then(style::Color::parse_ansi) {
+                        self.set_color(code == 38, color.into());
+                    }
+                }
+                39 => self.style.foreground_color = Color::Reset,
+                49 => self.style.background_color = Color::Reset,
+                30..=37 | 40..=47 | 90..=97 | 100..=107 => {
+                    let index = match code {
+                        90.. => code % 10 + 8,
+                        _ => code % 10,
+                    };
+                    self.set_color(matches!(code, 30..=37 | 90..=97), Color::from_index(index));
+                }
+                code => {
+                    if let Some(attribute) = attribute(code) {
+                        self.style.attribute = attribute;
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_color(&mut self, foreground: bool, color: Color) {
+        if foreground {
+            self.style.foreground_color = color;
+        } else {
+            self.style.background_color = color;
+        }
+    }
+}
+
+impl Write for VirtualScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        self.interpret();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Backend for VirtualScreen {
+    fn size(&self) -> Result<(u16, u16)> {
+        Ok((self.grid.width(), self.grid.height()))
+    }
+
+    fn wait_for_input(&mut self, _timeout: Option<Duration>) -> Result<Option<Input>> {
+        Ok(self.inputs.pop_front())
+    }
+}
+
+enum Escape<'a> {
+    /// A Control Sequence Introducer sequence, `ESC [ params command`
+    Csi {
+        params: &'a str,
+        command: char,
+    },
+    Other,
+}
+
+/// The escape sequence at the start of `text` and its length in bytes, or
+/// `None` if it is cut off
+fn escape_sequence(text: &str) -> Option<(Escape<'_>, usize)> {
+    let mut chars = text.char_indices().skip(1);
+    match chars.next()? {
+        (_, '[') => {
+            for (i, c) in chars {
+                // parameters and intermediate bytes run up to a final byte
+                if ('@'..='~').contains(&c) {
+                    return Some((
+                        Escape::Csi {
+                            params: &text[2..i],
+                            command: c,
+                        },
+                        i + 1,
+                    ));
+                }
+            }
+            None
+        }
+        (i, c) => Some((Escape::Other, i + c.len_utf8())),
+    }
+}
+
+/// The attribute turned on by an SGR code. Codes that turn an attribute off
+/// go back to no attribute, since a [`Style`] only holds one
+fn attribute(code: u8) -> Option<Attribute> {
+    let attribute = match code {
+        1 => Attribute::Bold,
+        2 => Attribute::Dim,
+        3 => Attribute::Italic,
+        4 => Attribute::Underlined,
+        5 => Attribute::SlowBlink,
+        6 => Attribute::RapidBlink,
+        7 => Attribute::Reverse,
+        8 => Attribute::Hidden,
+        9 => Attribute::CrossedOut,
+        22 => Attribute::NormalIntensity,
+        21..=29 => Attribute::Reset,
+        _ => return None,
+    };
+    Some(attribute)
+}
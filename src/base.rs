@@ -1,89 +1,436 @@
-use std::io::{stdout, Write};
+use std::{collections::BTreeMap, fs, path::Path};
 
+#[cfg(all(feature = "cli", feature = "crossterm"))]
 use crossterm::{
     cursor::MoveTo,
     queue,
-    style::{Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
-    terminal,
+    style::{Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
 };
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
 
-use crate::Config;
-
-pub fn draw_base(config: &Config) {
-    let base_type = config.base;
-    let mut stdout = stdout();
-    let (cols, rows) = terminal::size().unwrap(); // Get terminal size for centering
-
-    match base_type {
-        1 => {
-            let lines = [
-                ":___________./~~~\\.___________:",
-                " \\                           / ",
-                "  \\_________________________/ ",
-                "  (_)                     (_)",
-            ];
-            let base_width = 31; // The maximum width of the base art for base type 1
-            let start_pos = (cols / 2) - (base_width / 2);
-            let y = rows - lines.len() as u16;
-            queue!(
-                stdout,
-                SetAttribute(crossterm::style::Attribute::Bold),
-                SetForegroundColor(Color::AnsiValue(8)),
-                SetBackgroundColor(Color::Reset),
-                MoveTo(start_pos, y),
-                Print(":"),
-                SetForegroundColor(Color::AnsiValue(2)),
-                Print("___________"),
-                SetForegroundColor(Color::AnsiValue(11)),
-                Print("./~~~\\."),
-                SetForegroundColor(Color::AnsiValue(2)),
-                Print("___________"),
-                SetAttribute(crossterm::style::Attribute::Bold),
-                SetForegroundColor(Color::AnsiValue(8)),
-                Print(":"),
-                MoveTo(start_pos, y + 1),
-                Print(" \\                           / "),
-                MoveTo(start_pos, y + 2),
-                Print("  \\_________________________/ "),
-                MoveTo(start_pos, y + 3),
-                Print("  (_)                     (_)"),
-            )
-            .unwrap();
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+use crate::{backend::Backend, Config};
+use crate::{
+    bonsai::utility::Style,
+    style::{Attribute, Color},
+    theme::{BaseColors, ColorSpec},
+    Error, Result,
+};
+
+/// Where the color of a run of base art comes from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Paint {
+    /// The theme's pot color
+    Pot,
+    /// The theme's soil color
+    Soil,
+    /// The theme's trunk color
+    Trunk,
+    /// A color that doesn't change with the theme
+    Fixed(Color),
+}
+
+/// A plant base before it is colored by a theme
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Base {
+    /// Runs of art with the same paint, per line
+    pub lines: Vec<Vec<(Paint, String)>>,
+    pub attribute: Attribute,
+    /// Column and row of the art the trunk grows up from
+    pub anchor: (u16, u16),
+}
+
+/// A run of base art drawn in a single style
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+/// The ascii art of a plant base, split into colored spans per line
+pub struct BaseArt {
+    /// The maximum width of the base art
+    pub width: u16,
+    pub lines: Vec<Vec<Span>>,
+    /// Column and row of the art the trunk grows up from
+    pub anchor: (u16, u16),
+}
+
+impl BaseArt {
+    /// Top left corner of the base when its anchor is centered and it sits
+    /// at the bottom of a canvas
+    pub fn origin(&self, cols: u16, rows: u16) -> (u16, u16) {
+        (
+            (cols / 2).saturating_sub(self.anchor.0),
+            rows.saturating_sub(self.lines.len() as u16),
+        )
+    }
+
+    /// The narrowest canvas the base fits on. Wider than the art when the
+    /// anchor is off center, since the anchor is what gets centered
+    pub fn min_width(&self) -> u16 {
+        let left = self.anchor.0;
+        let right = self.width.saturating_sub(left);
+        // the anchor goes on column `cols / 2`, leaving `cols - cols / 2`
+        // columns for it and the art to its right
+        left.saturating_mul(2)
+            .max(right.saturating_mul(2).saturating_sub(1))
+            .max(self.width)
+    }
+
+    /// Fail with [`Error::TerminalTooSmall`] if the base doesn't fit on a
+    /// canvas of the given size
+    pub fn check_fits(&self, cols: u16, rows: u16) -> Result<()> {
+        let min_width = self.min_width();
+        let min_height = self.lines.len() as u16;
+        if cols < min_width || rows < min_height {
+            return Err(Error::TerminalTooSmall {
+                width: cols,
+                height: rows,
+                min_width,
+                min_height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Names of the built-in bases, in the order `--list-bases` shows them
+pub const BUILTIN_BASES: [&str; 7] = [
+    "large",
+    "small",
+    "round",
+    "rock",
+    "hanging",
+    "stone-lantern",
+    "none",
+];
+
+/// The built-in base for a name from [`BUILTIN_BASES`], or the numbers bases
+/// were chosen by before they had names
+pub fn base_name(name: &str) -> Option<&'static str> {
+    match name {
+        "0" => Some("none"),
+        "1" => Some("large"),
+        "2" => Some("small"),
+        _ => BUILTIN_BASES.iter().copied().find(|base| *base == name),
+    }
+}
+
+/// Parse a `--base` argument into the name of a built-in base
+pub fn parse_base_name(name: &str) -> Result<String, String> {
+    base_name(name).map(String::from).ok_or_else(|| {
+        format!(
+            "unknown base `{}`, the built-in bases are {}",
+            name,
+            BUILTIN_BASES.join(", ")
+        )
+    })
+}
+
+/// A base name or number, as found in config and save files
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum BaseSpec {
+    Number(u8),
+    Name(String),
+}
+
+impl BaseSpec {
+    /// The name of the built-in base, or why there is none
+    pub fn name(&self) -> Result<String, String> {
+        match self {
+            BaseSpec::Number(number) => parse_base_name(&number.to_string()),
+            BaseSpec::Name(name) => parse_base_name(name),
+        }
+    }
+}
+
+impl Base {
+    /// The built-in base with the given name or number, or `None` if it is
+    /// `none` or unknown
+    pub fn named(name: &str) -> Option<Base> {
+        let (art, paint, attribute, anchor): (&[&str], &[&str], _, _) = match base_name(name)? {
+            "large" => (
+                &[
+                    r":___________./~~~\.___________:",
+                    r" \                           / ",
+                    r"  \_________________________/ ",
+                    r"  (_)                     (_)",
+                ],
+                &["pssssssssssstttttttsssssssssssp"],
+                Attribute::Bold,
+                (15, 0),
+            ),
+            "small" => (
+                &[r"(---./~~~\.---)", r" (           ) ", r"  (_________)  "],
+                &["pssstttttttsssp"],
+                Attribute::NormalIntensity,
+                (7, 0),
+            ),
+            "round" => (
+                &[
+                    r" .---./~~~\.---. ",
+                    r"(               )",
+                    r" \             / ",
+                    r"  `-._______.-'  ",
+                ],
+                &[" pssstttttttsssp"],
+                Attribute::Bold,
+                (8, 0),
+            ),
+            "rock" => (
+                &[
+                    r"   __./~~~\.___",
+                    r"  /  ~~   ~~   \__",
+                    r" (________________)",
+                ],
+                &["   sstttttttsss", "     ss   ss"],
+                Attribute::Bold,
+                (8, 0),
+            ),
+            "hanging" => (
+                &[
+                    r"\___./~~~\.___/",
+                    r" \___________/",
+                    r"  \ \  |  / /",
+                    r"   \ \ | / /",
+                    r"    \_\|/_/",
+                    r"       O",
+                ],
+                &["pssstttttttsssp"],
+                Attribute::NormalIntensity,
+                (7, 0),
+            ),
+            "stone-lantern" => (
+                &[
+                    r"                  _A_",
+                    r"                 |[ ]|",
+                    r"                 /___\",
+                    r" ____./~~~\.______|_|___",
+                    r"(_______________________)",
+                ],
+                &["", "", "", " sssstttttttsssssspppsss"],
+                Attribute::Bold,
+                (8, 3),
+            ),
+            _ => return None,
+        };
+        let paint_for = |letter| match letter {
+            's' => Some(Paint::Soil),
+            't' => Some(Paint::Trunk),
+            _ => Some(Paint::Pot),
+        };
+        let lines = paint_lines(art, paint, paint_for).ok()?;
+        Some(Base {
+            lines,
+            attribute,
+            anchor,
+        })
+    }
+
+    /// The width of the widest line
+    pub fn width(&self) -> u16 {
+        self.lines
+            .iter()
+            .map(|line| line.iter().map(|(_, text)| text.width()).sum::<usize>())
+            .max()
+            .unwrap_or(0) as u16
+    }
+
+    /// Number of rows to keep clear for the base, from the row the trunk
+    /// grows out of down, including the gap between the tree and the base
+    pub fn height(&self) -> u16 {
+        (self.lines.len() as u16).saturating_sub(self.anchor.1) + 1
+    }
+
+    /// The art colored with the given theme colors
+    pub fn art(&self, colors: &BaseColors) -> BaseArt {
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|(paint, text)| Span {
+                        style: Style {
+                            attribute: self.attribute,
+                            foreground_color: match paint {
+                                Paint::Pot => colors.pot,
+                                Paint::Soil => colors.soil,
+                                Paint::Trunk => colors.trunk,
+                                Paint::Fixed(color) => *color,
+                            },
+                            background_color: Color::Reset,
+                        },
+                        text: text.clone(),
+                    })
+                    .collect()
+            })
+            .collect();
+        BaseArt {
+            width: self.width(),
+            lines,
+            anchor: self.anchor,
+        }
+    }
 
-            // flush
-            stdout.flush().unwrap();
+    /// Read a base file
+    pub fn read(path: &Path) -> Result<Base> {
+        let text = fs::read_to_string(path).map_err(Error::file(path))?;
+        Base::parse(path, &text)
+    }
+
+    /// Parse the text of a base file. `path` is only used in errors
+    pub fn parse(path: &Path, text: &str) -> Result<Base> {
+        let file: BaseFile = toml::from_str(text).map_err(|err| Error::parse(path, err))?;
+        let art: Vec<&str> = file.art.lines().collect();
+        if art.iter().all(|line| line.trim().is_empty()) {
+            return Err(Error::parse(path, "`art` is empty"));
+        }
+
+        let mut colors = BTreeMap::new();
+        for (key, spec) in file.colors {
+            let mut chars = key.chars();
+            let (Some(letter), None) = (chars.next(), chars.next()) else {
+                return Err(Error::parse(
+                    path,
+                    format!("color names must be a single letter, not `{}`", key),
+                ));
+            };
+            let color = spec
+                .color()
+                .map_err(|message| Error::parse(path, message))?;
+            colors.insert(letter, Paint::Fixed(color));
         }
-        2 => {
-            let base_width = 15; // The maximum width of the base art for base type 2
-            let start_pos = (cols / 2) - (base_width / 2);
-            let y = rows - 3; // Assuming there are always 3 lines for base type 2
-
-            // Adjust color and attribute settings before printing each part of the base art
-            queue!(
-                stdout,
-                SetAttribute(crossterm::style::Attribute::NormalIntensity), // Assuming you want to reset boldness here
-                SetForegroundColor(Color::AnsiValue(8)),
-                SetBackgroundColor(Color::Reset),
-                MoveTo(start_pos, y),
-                Print("("),
-                SetForegroundColor(Color::AnsiValue(2)),
-                Print("---"),
-                SetForegroundColor(Color::AnsiValue(11)),
-                Print("./~~~\\."),
-                SetForegroundColor(Color::AnsiValue(2)),
-                Print("---"),
-                SetForegroundColor(Color::AnsiValue(8)),
-                Print(")"),
-                MoveTo(start_pos, y + 1),
-                Print(" (           ) "),
-                MoveTo(start_pos, y + 2),
-                Print("  (_________)  "),
+        let paint_for = |letter: char| match letter {
+            _ if colors.contains_key(&letter) => Some(colors[&letter].clone()),
+            ' ' | 'p' => Some(Paint::Pot),
+            's' => Some(Paint::Soil),
+            't' => Some(Paint::Trunk),
+            _ => None,
+        };
+
+        let masks: Vec<&str> = file.paint.as_deref().unwrap_or("").lines().collect();
+        let lines = paint_lines(&art, &masks, paint_for).map_err(|(row, letter)| {
+            Error::parse(
+                path,
+                format!(
+                    "unknown paint `{}` on line {} of `paint`, use p, s, t or a letter from [colors]",
+                    letter,
+                    row + 1
+                ),
             )
-            .unwrap();
+        })?;
+
+        let mut base = Base {
+            lines,
+            attribute: if file.bold {
+                Attribute::Bold
+            } else {
+                Attribute::NormalIntensity
+            },
+            anchor: (0, 0),
+        };
+        base.anchor = match file.anchor {
+            Some(anchor) => {
+                let line = text[..anchor.span().start].matches('\n').count() + 1;
+                let error = |message| Error::parse(path, format!("line {}: {}", line, message));
+                let [column, row] = *anchor.get_ref();
+                if row as usize >= base.lines.len() {
+                    return Err(error("`anchor` is below the art"));
+                }
+                if column >= base.width() {
+                    return Err(error("`anchor` is right of the art"));
+                }
+                (column, row)
+            }
+            None => (base.width() / 2, 0),
+        };
+        Ok(base)
+    }
+}
 
-            // flush stdout to apply all queued actions
-            stdout.flush().unwrap();
+type PaintedLines = Vec<Vec<(Paint, String)>>;
+
+/// Split lines of art into runs of the same paint, picked by the letter at
+/// the same position in `masks`. Fails with the row and letter of the first
+/// letter that has no paint
+fn paint_lines(
+    art: &[&str],
+    masks: &[&str],
+    paint_for: impl Fn(char) -> Option<Paint>,
+) -> Result<PaintedLines, (usize, char)> {
+    let mut lines = Vec::new();
+    for (row, text) in art.iter().enumerate() {
+        let mask: Vec<char> = masks.get(row).map_or(vec![], |mask| mask.chars().collect());
+        let mut line: Vec<(Paint, String)> = Vec::new();
+        for (column, c) in text.chars().enumerate() {
+            let letter = mask.get(column).copied().unwrap_or(' ');
+            let paint = paint_for(letter).ok_or((row, letter))?;
+            match line.last_mut() {
+                Some((last, run)) if *last == paint => run.push(c),
+                _ => line.push((paint, c.to_string())),
+            }
         }
-        _ => {}
+        lines.push(line);
     }
+    Ok(lines)
+}
+
+/// Parse a `--base-file` argument
+pub fn parse_base_file(path: &str) -> Result<Base, String> {
+    Base::read(Path::new(path)).map_err(|err| err.to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaseFile {
+    art: String,
+    paint: Option<String>,
+    #[serde(default)]
+    bold: bool,
+    anchor: Option<toml::Spanned<[u16; 2]>>,
+    #[serde(default)]
+    colors: BTreeMap<String, ColorSpec>,
+}
+
+/// The art for the named built-in base, or `None` if the base is empty
+pub fn base_art(name: &str, colors: &BaseColors) -> Option<BaseArt> {
+    Base::named(name).map(|base| base.art(colors))
+}
+
+/// Number of rows to keep clear for the given base, including the gap
+/// between the tree and the base
+pub fn base_height(base: Option<&Base>) -> u16 {
+    base.map_or(0, Base::height)
+}
+
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+pub fn draw_base(out: &mut impl Backend, config: &Config) -> Result<()> {
+    let Some(art) = config.base_art() else {
+        return Ok(());
+    };
+    let (cols, rows) = out.size()?; // Get terminal size for centering
+    art.check_fits(cols, rows)?;
+    let (start_pos, y) = art.origin(cols, rows);
+    let colors = config.color_support();
+
+    for (i, line) in art.lines.iter().enumerate() {
+        queue!(out, MoveTo(start_pos, y + i as u16))?;
+        for span in line {
+            if colors.enabled() {
+                queue!(
+                    out,
+                    SetAttribute(span.style.attribute.into()),
+                    SetForegroundColor(colors.adapt(span.style.foreground_color).into()),
+                    SetBackgroundColor(colors.adapt(span.style.background_color).into()),
+                )?;
+            }
+            queue!(out, Print(&span.text))?;
+        }
+    }
+
+    // flush to apply all queued actions
+    out.flush()?;
+    Ok(())
 }
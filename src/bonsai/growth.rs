@@ -0,0 +1,173 @@
+use crate::rng::TreeRng;
+
+use super::{
+    utility::{choose_color, choose_string, set_deltas, Style},
+    BranchType, GrowthParams, Position,
+};
+
+/// A branch as it grows, one step at a time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub branch_type: BranchType,
+    /// Where the branch is. It moves after [`GrowthStrategy::spawn`], so
+    /// the glyph and style are chosen for the new position
+    pub pos: Position,
+    /// Steps left to grow after this one
+    pub life: i32,
+    /// Steps since the tree started growing, as the starting life of the
+    /// tree minus `life`
+    pub age: i32,
+    /// Steps until the branch can grow another shoot. Strategies that don't
+    /// use it can leave it alone
+    pub shoot_cooldown: i32,
+}
+
+/// A new branch to grow out of the current position of a branch
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Spawn {
+    pub branch_type: BranchType,
+    pub life: i32,
+}
+
+/// The rules a tree grows by. The canvas, bounds checking and building the
+/// tree are taken care of, a strategy only decides how each branch grows
+///
+/// Every step of a branch calls [`deltas`], then [`spawn`], then if the
+/// branch is still on the canvas after moving, [`glyph`] and [`style`]
+///
+/// [`deltas`]: GrowthStrategy::deltas
+/// [`spawn`]: GrowthStrategy::spawn
+/// [`glyph`]: GrowthStrategy::glyph
+/// [`style`]: GrowthStrategy::style
+pub trait GrowthStrategy {
+    /// Called once before the trunk starts growing
+    fn start(&mut self, _params: &GrowthParams, _rng: &mut TreeRng) {}
+
+    /// How far the branch moves this step. Moving down is limited when the
+    /// branch is close to the ground
+    fn deltas(&mut self, params: &GrowthParams, branch: &Branch, rng: &mut TreeRng) -> (i32, i32);
+
+    /// A branch to grow out of this one before it moves, if any. It is
+    /// grown to the end before this branch carries on
+    fn spawn(
+        &mut self,
+        params: &GrowthParams,
+        branch: &mut Branch,
+        rng: &mut TreeRng,
+    ) -> Option<Spawn>;
+
+    /// The text drawn where the branch moved to
+    fn glyph(
+        &mut self,
+        params: &GrowthParams,
+        branch: &Branch,
+        deltas: (i32, i32),
+        rng: &mut TreeRng,
+    ) -> String;
+
+    /// The style the glyph is drawn in
+    fn style(&mut self, params: &GrowthParams, branch: &Branch, rng: &mut TreeRng) -> Style;
+}
+
+/// The growth rules of cbonsai, shaped by the species in the growth
+/// parameters
+#[derive(Clone, Debug, Default)]
+pub struct ClassicGrowth {
+    /// Shoots go left and right of the trunk in turn
+    shoot_counter: i32,
+}
+
+impl GrowthStrategy for ClassicGrowth {
+    fn start(&mut self, _params: &GrowthParams, rng: &mut TreeRng) {
+        // Initialize shoot counter to a random value
+        self.shoot_counter = rng.range(1, 4);
+    }
+
+    fn deltas(&mut self, params: &GrowthParams, branch: &Branch, rng: &mut TreeRng) -> (i32, i32) {
+        set_deltas(
+            &params.species,
+            &branch.branch_type,
+            branch.life,
+            branch.age,
+            params.multiplier,
+            rng,
+        )
+    }
+
+    fn spawn(
+        &mut self,
+        params: &GrowthParams,
+        branch: &mut Branch,
+        rng: &mut TreeRng,
+    ) -> Option<Spawn> {
+        let species = &params.species;
+        let life = branch.life;
+        let mut spawn = None;
+
+        if life < 3 {
+            spawn = Some(Spawn {
+                branch_type: BranchType::Dead,
+                life,
+            });
+        } else {
+            match branch.branch_type {
+                BranchType::Trunk | BranchType::ShootLeft | BranchType::ShootRight
+                    if life < (params.multiplier + 2) =>
+                {
+                    spawn = Some(Spawn {
+                        branch_type: BranchType::Dying,
+                        life,
+                    });
+                }
+                BranchType::Trunk
+                    if (rng.one_in(species.branch_chance) || life % params.multiplier == 0) =>
+                {
+                    if rng.one_in(species.split_chance) && life > 7 {
+                        branch.shoot_cooldown = params.multiplier * species.shoot_spacing;
+                        spawn = Some(Spawn {
+                            branch_type: BranchType::Trunk,
+                            life: life + rng.range(-2, 3),
+                        });
+                    } else if branch.shoot_cooldown <= 0 {
+                        branch.shoot_cooldown = params.multiplier * species.shoot_spacing;
+                        self.shoot_counter += 1;
+                        let shoot_direction = if self.shoot_counter % 2 == 0 {
+                            BranchType::ShootLeft
+                        } else {
+                            BranchType::ShootRight
+                        };
+                        spawn = Some(Spawn {
+                            branch_type: shoot_direction,
+                            life: life + params.multiplier + species.shoot_life,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        branch.shoot_cooldown -= 1;
+        spawn
+    }
+
+    fn glyph(
+        &mut self,
+        params: &GrowthParams,
+        branch: &Branch,
+        (dx, dy): (i32, i32),
+        rng: &mut TreeRng,
+    ) -> String {
+        choose_string(
+            &params.leaves,
+            &branch.branch_type,
+            branch.life,
+            dx,
+            dy,
+            rng,
+        )
+    }
+
+    fn style(&mut self, params: &GrowthParams, branch: &Branch, rng: &mut TreeRng) -> Style {
+        choose_color(&branch.branch_type, &params.colors, rng)
+    }
+}
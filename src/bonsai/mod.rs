@@ -1,6 +1,8 @@
+pub mod growth;
 pub mod utility;
+#[cfg(all(feature = "cli", feature = "crossterm"))]
 use crossterm::{
-    cursor::{self, MoveTo},
+    cursor::MoveTo,
     execute,
     style::{
         Color, Colors, Print, SetAttribute, SetBackgroundColor, SetColors, SetForegroundColor,
@@ -8,39 +10,151 @@ use crossterm::{
     terminal::{self, Clear},
     QueueableCommand,
 };
-use rand::{rngs::StdRng, Rng};
-use std::{
-    io::{stdout, Write},
-    thread,
-    time::{Duration, Instant},
-};
-use utility::{check_key_press, choose_color, choose_string, set_deltas};
+use growth::{Branch, ClassicGrowth, GrowthStrategy};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+use std::time::Duration;
 
-use crate::{base::draw_base, Config};
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+use self::utility::{visible, Input};
+#[cfg(feature = "cli")]
+use crate::Config;
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+use crate::{backend::Backend, base::draw_base};
+use crate::{
+    base::{base_height, Base},
+    rng::TreeRng,
+    species::Species,
+    Error, Result,
+};
 
-use self::utility::Style;
+use self::utility::{Style, TreeColors};
 
 /// The different types of each symbol
-pub(crate) enum BranchType {
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BranchType {
     /// The main trunk of the tree
     Trunk,
     /// A branch that grows to the left
     ShootLeft,
     /// A branch that grows to the right
     ShootRight,
-    /// A branch that is dying
-    Dead,
     /// A branch that is dead
+    Dead,
+    /// A branch that is dying
     Dying,
 }
 
+impl fmt::Display for BranchType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Everything that controls how a tree grows, apart from the random seed and
+/// the canvas
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GrowthParams {
+    /// The starting life of the tree; higher -> bigger tree
+    pub life: i32,
+    /// The branch multiplier; higher -> less branches
+    pub multiplier: i32,
+    /// Strings randomly chosen for leaves
+    pub leaves: Vec<String>,
+    pub colors: TreeColors,
+    /// The shape of the tree
+    pub species: Species,
+}
+
+impl Default for GrowthParams {
+    fn default() -> Self {
+        GrowthParams {
+            life: 32,
+            multiplier: 3,
+            leaves: vec!["&".to_string()],
+            colors: TreeColors::default(),
+            species: Species::default(),
+        }
+    }
+}
+
+impl GrowthParams {
+    #[cfg(feature = "cli")]
+    pub fn from_config(config: &Config) -> Self {
+        GrowthParams {
+            life: config.life,
+            multiplier: config.multiplier,
+            leaves: config.leaves(),
+            colors: config.theme().tree,
+            species: config.species(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.multiplier < 1 {
+            return Err(Error::InvalidConfig(format!(
+                "the branch multiplier must be at least 1, got {}",
+                self.multiplier
+            )));
+        }
+        let species = &self.species;
+        if species.branch_chance < 1 || species.split_chance < 1 {
+            return Err(Error::InvalidConfig(format!(
+                "the species branch and split chances must be at least 1, got {} and {}",
+                species.branch_chance, species.split_chance
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Totals gathered while growing a tree
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeStats {
+    /// Number of branches grown, including the trunk
+    pub branches: i32,
+    /// Number of shoots that split off of the trunk
+    pub shoots: i32,
+}
+
 struct Counters {
     shoots: i32,
     branches: i32,
-    shoot_counter: i32,
     tree_bottom: u16,
+    max_x: u16,
+    max_y: u16,
+}
+
+/// The area a tree is allowed to grow in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Canvas {
+    pub width: u16,
+    pub height: u16,
+    /// Rows at the bottom of the canvas reserved for the base
+    pub base_height: u16,
 }
 
+impl Canvas {
+    /// A canvas of the given size with room left for `base` at the bottom
+    pub fn new(width: u16, height: u16, base: Option<&Base>) -> Self {
+        Canvas {
+            width,
+            height,
+            base_height: base_height(base),
+        }
+    }
+
+    /// The row the trunk starts on, just above the base, or the bottom row
+    /// without a base
+    pub fn ground(&self) -> u16 {
+        self.height
+            .saturating_sub(self.base_height)
+            .min(self.height.saturating_sub(1))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Val {
     pub style: Style,
     pub char: String,
@@ -48,250 +162,227 @@ pub struct Val {
     pub dx: i32,
     pub dy: i32,
     pub life: i32,
-    pub branch_type: String,
+    pub branch_type: BranchType,
     pub shoots: i32,
     pub shoot_cooldown: i32,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-pub fn grow_tree(config: &Config, rng: &mut StdRng) -> Vec<Val> {
-    let mut stdout = stdout();
-    let (max_x, mut max_y) = terminal::size().unwrap();
-    max_y = match config.base {
-        1 => max_y - 5,
-        2 => max_y - 4,
-        _ => max_y,
-    };
+/// Grow a tree on the given canvas, or draw the L-system if there is one.
+/// This does no I/O, so the same config, seed and canvas always produce the
+/// same tree
+#[cfg(feature = "cli")]
+pub fn grow_tree(config: &Config, rng: &mut TreeRng, canvas: Canvas) -> Vec<Val> {
+    let params = GrowthParams::from_config(config);
+    match &config.lsystem {
+        Some(lsystem) => lsystem.grow(&params, rng, canvas),
+        None => grow(&params, rng, canvas).0,
+    }
+}
+
+/// Grow a tree from explicit parameters, returning the tree along with
+/// statistics about its growth
+pub fn grow(params: &GrowthParams, rng: &mut TreeRng, canvas: Canvas) -> (Vec<Val>, TreeStats) {
+    grow_with(&mut ClassicGrowth::default(), params, rng, canvas)
+}
+
+/// Grow a tree by the rules of a custom [`GrowthStrategy`]
+pub fn grow_with(
+    strategy: &mut impl GrowthStrategy,
+    params: &GrowthParams,
+    rng: &mut TreeRng,
+    canvas: Canvas,
+) -> (Vec<Val>, TreeStats) {
+    let max_y = canvas.ground();
 
     // Reset counters
     let mut counters = Counters {
         shoots: 0,
         branches: 0,
-        // Initialize shoot counter to a random value
-        shoot_counter: (rng.gen::<i32>() % 3) + 1,
         tree_bottom: max_y,
+        max_x: canvas.width,
+        max_y: canvas.height,
     };
-
-    if config.verbose {
-        execute!(
-            stdout,
-            cursor::MoveTo(5, 2),
-            Print(format!("maxX: {:03}, maxY: {:03}", max_x, max_y)),
-        )
-        .unwrap();
-    }
+    strategy.start(params, rng);
 
     let mut tree = Vec::new();
 
     // Recursively grow tree trunk and branches
     branch(
-        config,
+        strategy,
+        params,
         &mut counters,
         &mut tree,
         rng,
-        Position {
-            x: (max_x / 2) as i32,
-            y: max_y as i32,
-        },
-        BranchType::Trunk,
-        config.life,
+        sprout(
+            params,
+            BranchType::Trunk,
+            Position {
+                x: (canvas.width / 2) as i32,
+                y: max_y as i32,
+            },
+            params.life,
+        ),
     );
 
-    tree
+    let stats = TreeStats {
+        branches: counters.branches,
+        shoots: counters.shoots,
+    };
+    (tree, stats)
+}
+
+/// A new branch starting at `pos`
+fn sprout(params: &GrowthParams, branch_type: BranchType, pos: Position, life: i32) -> Branch {
+    Branch {
+        branch_type,
+        pos,
+        life,
+        age: 0,
+        shoot_cooldown: params.multiplier,
+    }
 }
 
 fn branch(
-    config: &Config,
+    strategy: &mut impl GrowthStrategy,
+    params: &GrowthParams,
     counters: &mut Counters,
     tree: &mut Vec<Val>,
-    rng: &mut StdRng,
-    mut pos: Position,
-    branch_type: BranchType,
-    mut life: i32,
+    rng: &mut TreeRng,
+    mut current: Branch,
 ) {
     counters.branches += 1;
-    let mut shoot_cooldown = config.multiplier;
+    let branch_type = current.branch_type;
 
-    // This is a highly simplified loop to mimic the growth logic
-    while life > 0 {
+    while current.life > 0 {
         // Decrement life
-        life -= 1;
-        let age = config.life - life;
+        current.life -= 1;
+        current.age = params.life - current.life;
 
-        let (dx, mut dy) = set_deltas(&branch_type, life, age, config.multiplier, rng);
-        let (max_x, max_y) = terminal::size().unwrap();
+        let (dx, mut dy) = strategy.deltas(params, &current, rng);
+        let (max_x, max_y) = (counters.max_x, counters.max_y);
 
-        if dy > 0 && pos.y > (counters.tree_bottom as i32 - 1) {
+        if dy > 0 && current.pos.y > (counters.tree_bottom as i32 - 1) {
             dy -= 1;
         } // reduce dy if too close to the ground
-          // Ensure x and y are within terminal bounds
-        if pos.x < 0 || pos.x as u16 >= max_x || pos.y < 0 || pos.y as u16 >= max_y {
+        if !on_canvas(current.pos, max_x, max_y) {
             break;
         }
 
-        if life < 3 {
-            branch(config, counters, tree, rng, pos, BranchType::Dead, life);
-        } else {
-            match branch_type {
-                BranchType::Trunk | BranchType::ShootLeft | BranchType::ShootRight
-                    if life < (config.multiplier + 2) =>
-                {
-                    branch(config, counters, tree, rng, pos, BranchType::Dying, life);
-                }
-                BranchType::Trunk
-                    if (rng.gen_range(0..3) == 0 || life % config.multiplier == 0) =>
-                {
-                    if rng.gen_range(0..8) == 0 && life > 7 {
-                        shoot_cooldown = config.multiplier * 2;
-                        let random_life = life + rng.gen_range(-2..3);
-                        branch(
-                            config,
-                            counters,
-                            tree,
-                            rng,
-                            pos,
-                            BranchType::Trunk,
-                            random_life,
-                        );
-                    } else if shoot_cooldown <= 0 {
-                        shoot_cooldown = config.multiplier * 2;
-                        let shoot_life = life + config.multiplier;
-                        counters.shoots += 1;
-                        counters.shoot_counter += 1;
-                        let shoot_direction = if counters.shoot_counter % 2 == 0 {
-                            BranchType::ShootLeft
-                        } else {
-                            BranchType::ShootRight
-                        };
-                        branch(
-                            config,
-                            counters,
-                            tree,
-                            rng,
-                            pos,
-                            shoot_direction,
-                            shoot_life,
-                        );
-                    }
-                }
-                _ => {}
+        if let Some(spawn) = strategy.spawn(params, &mut current, rng) {
+            if matches!(
+                spawn.branch_type,
+                BranchType::ShootLeft | BranchType::ShootRight
+            ) {
+                counters.shoots += 1;
             }
+            branch(
+                strategy,
+                params,
+                counters,
+                tree,
+                rng,
+                sprout(params, spawn.branch_type, current.pos, spawn.life),
+            );
         }
 
-        shoot_cooldown -= 1;
-
         // Update x and y for the next iteration
-        pos.x += dx;
-        pos.y += dy;
+        current.pos.x += dx;
+        current.pos.y += dy;
 
-        if pos.x < 0 || pos.x as u16 >= max_x || pos.y < 0 || pos.y as u16 >= max_y {
+        if !on_canvas(current.pos, max_x, max_y) {
             continue;
         }
 
         // Drawing the branch part
-        let branch_str = choose_string(config, &branch_type, life, dx, dy);
-        // Example to set color, adjust as needed
-        let style = choose_color(&branch_type, rng).unwrap();
-        let type_str = match branch_type {
-            BranchType::Trunk => "Trunk",
-            BranchType::ShootLeft => "ShootLeft",
-            BranchType::ShootRight => "ShootRight",
-            BranchType::Dying => "Dying",
-            BranchType::Dead => "Dead",
-        };
-        let val = Val {
-            pos,
+        let char = strategy.glyph(params, &current, (dx, dy), rng);
+        let style = strategy.style(params, &current, rng);
+        tree.push(Val {
+            pos: current.pos,
             style,
-            char: branch_str,
-            branch_type: type_str.into(),
+            char,
+            branch_type,
             dx,
             dy,
-            life,
+            life: current.life,
             shoots: counters.shoots,
-            shoot_cooldown,
-        };
-        tree.push(val);
+            shoot_cooldown: current.shoot_cooldown,
+        });
     }
 }
 
-// returns true if the tree finished drawing. Returns false if it didn't and
-// the user chose to exit early
-pub fn draw_tree(config: &Config, tree: &Vec<Val>) -> bool {
-    let mut stdout = stdout();
+fn on_canvas(pos: Position, max_x: u16, max_y: u16) -> bool {
+    pos.x >= 0 && (pos.x as u16) < max_x && pos.y >= 0 && (pos.y as u16) < max_y
+}
+
+// returns None if the tree finished drawing. Returns the input that stopped
+// it if the user chose to exit early or the terminal was resized
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+pub fn draw_tree(
+    out: &mut impl Backend,
+    config: &Config,
+    tree: &Vec<Val>,
+) -> Result<Option<Input>> {
+    let colors = config.color_support();
+    let (cols, rows) = out.size()?;
     for val in tree {
         if config.verbose {
             // Queueing the commands instead of executing them immediately
             // This allows for batching the writes, which can be more efficient
-            stdout
-                .queue(MoveTo(5, 3))
-                .unwrap()
-                .queue(Print(format!("life: {}", val.life)))
-                .unwrap()
-                .queue(MoveTo(5, 4))
-                .unwrap()
-                .queue(Print(format!("shoots: {:02}", val.shoots)))
-                .unwrap()
-                .queue(MoveTo(5, 5))
-                .unwrap()
-                .queue(Print(format!("dx: {:02}", val.dx)))
-                .unwrap()
-                .queue(MoveTo(5, 6))
-                .unwrap()
-                .queue(Print(format!("dy: {:02}", val.dy)))
-                .unwrap()
-                .queue(MoveTo(5, 7))
-                .unwrap()
-                .queue(Print(format!("type: {}", val.branch_type)))
-                .unwrap()
-                .queue(MoveTo(5, 8))
-                .unwrap()
-                .queue(Print(format!("shootCooldown: {:3}", val.shoot_cooldown)))
-                .unwrap();
-
-            // Flush the stdout to apply the queued operations
-            stdout.flush().unwrap();
+            out.queue(MoveTo(5, 3))?
+                .queue(Print(format!("life: {}", val.life)))?
+                .queue(MoveTo(5, 4))?
+                .queue(Print(format!("shoots: {:02}", val.shoots)))?
+                .queue(MoveTo(5, 5))?
+                .queue(Print(format!("dx: {:02}", val.dx)))?
+                .queue(MoveTo(5, 6))?
+                .queue(Print(format!("dy: {:02}", val.dy)))?
+                .queue(MoveTo(5, 7))?
+                .queue(Print(format!("type: {}", val.branch_type)))?
+                .queue(MoveTo(5, 8))?
+                .queue(Print(format!("shootCooldown: {:3}", val.shoot_cooldown)))?;
+
+            // Flush the output to apply the queued operations
+            out.flush()?;
         }
 
-        let _ = execute!(
-            stdout,
-            SetAttribute(val.style.attribute),
-            SetForegroundColor(val.style.foreground_color),
-            SetBackgroundColor(val.style.background_color),
-        );
-        let _ = execute!(
-            stdout,
-            MoveTo(val.pos.x as u16, val.pos.y as u16),
-            Print(val.char.clone()),
-        );
-        // reset color
-        let _ = execute!(stdout, SetColors(Colors::new(Color::Reset, Color::Reset)),);
-        if config.live {
-            let start = Instant::now();
-            let mut finished = false;
-            while start.elapsed() < Duration::from_secs_f64(config.time) {
-                if check_key_press() {
-                    finished = true;
-                    break;
-                }
-                thread::sleep(Duration::from_millis(50)); // Sleep to avoid busy-waiting
+        let visible = (0..rows as i32)
+            .contains(&val.pos.y)
+            .then(|| visible(val.pos.x, &val.char, cols))
+            .flatten();
+        if let Some((x, text)) = visible {
+            if colors.enabled() {
+                execute!(
+                    out,
+                    SetAttribute(val.style.attribute.into()),
+                    SetForegroundColor(colors.adapt(val.style.foreground_color).into()),
+                    SetBackgroundColor(colors.adapt(val.style.background_color).into()),
+                )?;
             }
-
-            if finished {
-                return false;
+            execute!(out, MoveTo(x, val.pos.y as u16), Print(text))?;
+            if colors.enabled() {
+                // reset color
+                execute!(out, SetColors(Colors::new(Color::Reset, Color::Reset)),)?;
+            }
+        }
+        if config.live {
+            let input = out.wait_for_input(Some(Duration::from_secs_f64(config.time)))?;
+            if input.is_some() {
+                return Ok(input);
             }
         }
     }
 
-    true
+    Ok(None)
 }
 
-pub fn init(args: &Config) {
-    let mut stdout = stdout();
-    execute!(stdout, Clear(terminal::ClearType::All)).unwrap();
-    draw_base(args);
+#[cfg(all(feature = "cli", feature = "crossterm"))]
+pub fn init(out: &mut impl Backend, args: &Config) -> Result<()> {
+    execute!(out, Clear(terminal::ClearType::All))?;
+    draw_base(out, args)
 }
@@ -1,125 +1,82 @@
-use std::{
-    error::Error,
-    io::{stdout, Write},
-    time::Duration,
-};
+#[cfg(feature = "crossterm")]
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "crossterm")]
 use crossterm::{
     cursor::MoveTo,
-    event::{self, Event, KeyEventKind},
-    execute, queue,
-    style::{Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
-    terminal::size,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
 };
-use rand::{rngs::StdRng, Rng};
-
-use crate::Config;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "crossterm")]
+use unicode_width::UnicodeWidthChar;
 
 use super::BranchType;
+#[cfg(feature = "crossterm")]
+use crate::{backend::Backend, terminal::check_signal, theme::ColorSupport, Error, Result};
+use crate::{
+    rng::TreeRng,
+    species::Species,
+    style::{Attribute, Color},
+};
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Style {
     pub attribute: Attribute,
     pub foreground_color: Color,
     pub background_color: Color,
 }
 
+impl Default for Style {
+    /// The terminal's default colors with no attributes
+    fn default() -> Self {
+        Style {
+            attribute: Attribute::Reset,
+            foreground_color: Color::Reset,
+            background_color: Color::Reset,
+        }
+    }
+}
+
 pub(crate) fn set_deltas(
+    species: &Species,
     branch_type: &BranchType,
     life: i32,
     age: i32,
     multiplier: i32,
-    rng: &mut StdRng,
+    rng: &mut TreeRng,
 ) -> (i32, i32) {
-    let (dx, dy): (i32, i32);
-
-    match branch_type {
-        BranchType::Trunk => {
-            if age <= 2 || life < 4 {
-                dy = 0;
-                dx = rng.gen_range(-1..=1);
-            } else if age < (multiplier * 3) {
-                if age % (multiplier / 2) == 0 {
-                    dy = -1;
-                } else {
-                    dy = 0;
-                }
-
-                dx = match rng.gen_range(0..10) {
-                    0 => -2,
-                    1..=3 => -1,
-                    4..=5 => 0,
-                    6..=8 => 1,
-                    _ => 2,
-                };
+    let deltas = match branch_type {
+        BranchType::Trunk if age <= 2 || life < 4 => &species.sapling,
+        BranchType::Trunk if age < (multiplier * species.spread_steps) => {
+            let dy = if age % (multiplier / 2).max(1) == 0 {
+                -1
             } else {
-                if rng.gen_range(0..10) > 2 {
-                    dy = -1;
-                } else {
-                    dy = 0;
-                }
-                dx = rng.gen_range(-1..=1);
-            }
-        }
-        BranchType::ShootLeft => {
-            dy = match rng.gen_range(0..10) {
-                0..=1 => -1,
-                2..=7 => 0,
-                _ => 1,
-            };
-            dx = match rng.gen_range(0..10) {
-                0..=1 => -2,
-                2..=5 => -1,
-                6..=8 => 0,
-                _ => 1,
+                0
             };
+            return (species.spread.pick(rng), dy);
         }
+        BranchType::Trunk => &species.trunk,
+        BranchType::ShootLeft => &species.shoot,
         BranchType::ShootRight => {
-            dy = match rng.gen_range(0..10) {
-                0..=1 => -1,
-                2..=7 => 0,
-                _ => 1,
-            };
-            dx = match rng.gen_range(0..10) {
-                0..=1 => 2,
-                2..=5 => 1,
-                6..=8 => 0,
-                _ => -1,
-            };
-        }
-        BranchType::Dying => {
-            dy = match rng.gen_range(0..10) {
-                0..=1 => -1,
-                2..=8 => 0,
-                _ => 1,
-            };
-            dx = match rng.gen_range(0..15) {
-                0 => -3,
-                1..=2 => -2,
-                3..=5 => -1,
-                6..=8 => 0,
-                9..=11 => 1,
-                12..=13 => 2,
-                _ => 3,
-            };
+            let dy = species.shoot.dy.pick(rng);
+            return (species.shoot.dx.mirrored().pick(rng), dy);
         }
-        BranchType::Dead => {
-            dy = match rng.gen_range(0..10) {
-                0..=2 => -1,
-                3..=6 => 0,
-                _ => 1,
-            };
-            dx = rng.gen_range(-1..=1);
-        }
-    }
-
-    (dx, dy)
+        BranchType::Dying => &species.dying,
+        BranchType::Dead => &species.dead,
+    };
+    let dy = deltas.dy.pick(rng);
+    (deltas.dx.pick(rng), dy)
 }
+
 pub(crate) fn choose_string(
-    _conf: &Config,
+    leaves: &[String],
     branch_type: &BranchType,
     life: i32,
     dx: i32,
     dy: i32,
+    rng: &mut TreeRng,
 ) -> String {
     let mut branch_str = match branch_type {
         BranchType::Trunk => match (dx, dy) {
@@ -145,105 +102,105 @@ pub(crate) fn choose_string(
             _ if dx > 0 => "/".to_string(),
             _ => "?".to_string(), // Fallback
         },
-        BranchType::Dying | BranchType::Dead => {
-            "&".to_string() // Fallback
-                            // add the below if user input leaves become allowedc
-                            // conf.leaves[rng.gen_range(0..conf.leaves.len())].clone()
-        }
+        BranchType::Dying | BranchType::Dead => choose_leaf(leaves, rng),
     };
 
     // If life < 4, override with dying or dead branch representation
     if life < 4 {
-        branch_str = "&".to_string(); // Fallback
-                                      // add the below if user input leaves become allowedc
-                                      // return conf.leaves[rng.gen_range(0..conf.leaves.len())].clone();
+        branch_str = choose_leaf(leaves, rng);
     }
 
     branch_str
 }
 
+/// Pick one of the user supplied leaves. With a single leaf the rng is left
+/// untouched so seeds grow the same tree as they did before `--leaf` existed
+fn choose_leaf(leaves: &[String], rng: &mut TreeRng) -> String {
+    let leaves: Vec<&String> = leaves.iter().filter(|leaf| !leaf.is_empty()).collect();
+    match leaves.len() {
+        0 => "&".to_string(),
+        1 => leaves[0].clone(),
+        len => leaves[rng.range(0, len as i32) as usize].clone(),
+    }
+}
+
+/// The colors used for each type of branch. Wood is drawn in either of its
+/// two colors at random, leaves are sometimes drawn bold
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TreeColors {
+    pub trunk: Color,
+    /// Bold trunk color
+    pub trunk_bright: Color,
+    /// Color of the shoots growing left and right off of the trunk
+    pub shoot: Color,
+    /// Bold shoot color
+    pub shoot_bright: Color,
+    pub dying: Color,
+    pub dead: Color,
+}
+
+impl Default for TreeColors {
+    fn default() -> Self {
+        TreeColors {
+            trunk: Color::AnsiValue(3),
+            trunk_bright: Color::AnsiValue(11),
+            shoot: Color::AnsiValue(3),
+            shoot_bright: Color::AnsiValue(11),
+            dying: Color::AnsiValue(2),
+            dead: Color::AnsiValue(10),
+        }
+    }
+}
+
 pub(crate) fn choose_color(
     branch_type: &BranchType,
-    rng: &mut StdRng,
-) -> Result<Style, std::io::Error> {
-    let mut stdout = stdout();
-
-    // Default background color
-    let bg = Color::Reset; // Using Reset to use terminal's default
-    let mut style = Style {
-        attribute: Attribute::Reset,
-        foreground_color: Color::Reset,
-        background_color: bg,
-    };
+    colors: &TreeColors,
+    rng: &mut TreeRng,
+) -> Style {
+    let mut style = Style::default();
 
     match branch_type {
         BranchType::Trunk | BranchType::ShootLeft | BranchType::ShootRight => {
-            if rng.gen_range(0..2) == 0 {
+            let (wood, wood_bright) = match branch_type {
+                BranchType::Trunk => (colors.trunk, colors.trunk_bright),
+                _ => (colors.shoot, colors.shoot_bright),
+            };
+            if rng.one_in(2) {
                 style.attribute = Attribute::Bold;
-                style.foreground_color = Color::AnsiValue(11);
+                style.foreground_color = wood_bright;
             } else {
-                execute!(
-                    stdout,
-                    SetForegroundColor(Color::AnsiValue(3)),
-                    SetBackgroundColor(bg),
-                )?;
-                style.foreground_color = Color::AnsiValue(3);
+                style.foreground_color = wood;
             }
         }
         BranchType::Dying => {
-            if rng.gen_range(0..10) == 0 {
-                execute!(
-                    stdout,
-                    SetAttribute(Attribute::Bold),
-                    SetForegroundColor(Color::AnsiValue(2)),
-                    SetBackgroundColor(bg),
-                )?;
+            if rng.one_in(10) {
                 style.attribute = Attribute::Bold;
-                style.foreground_color = Color::AnsiValue(2);
-            } else {
-                execute!(
-                    stdout,
-                    SetForegroundColor(Color::AnsiValue(2)),
-                    SetBackgroundColor(bg),
-                )?;
-                style.foreground_color = Color::AnsiValue(2);
             }
+            style.foreground_color = colors.dying;
         }
         BranchType::Dead => {
-            if rng.gen_range(0..3) == 0 {
-                execute!(
-                    stdout,
-                    SetAttribute(Attribute::Bold),
-                    SetForegroundColor(Color::AnsiValue(10)),
-                    SetBackgroundColor(bg),
-                )?;
+            if rng.one_in(3) {
                 style.attribute = Attribute::Bold;
-                style.foreground_color = Color::AnsiValue(10);
-            } else {
-                execute!(
-                    stdout,
-                    SetForegroundColor(Color::AnsiValue(10)),
-                    SetBackgroundColor(bg),
-                )?;
-                style.foreground_color = Color::AnsiValue(10);
             }
+            style.foreground_color = colors.dead;
         }
     }
 
-    Ok(style)
+    style
 }
-pub fn create_message_window(message: &str) -> Result<(), Box<dyn Error>> {
-    let mut stdout = stdout();
-
-    // Get terminal size
-    let (max_x, max_y) = size()?;
-
+/// Lay out the message box for a canvas of the given size. Returns the text
+/// to print and the column and row to print it at
+///
+/// A long message on a small canvas can need a box far bigger than the
+/// canvas, so anything past its edges is left to be clipped when drawn
+pub fn message_window(message: &str, max_x: u16, max_y: u16) -> Vec<(i32, i32, String)> {
     // Calculate box dimensions based on message length
-    let message_length = message.chars().count() as u16;
-    let (box_width, box_height) = if message_length + 3 <= (0.25 * max_x as f32) as u16 {
+    let message_length = message.chars().count().min(i32::MAX as usize / 2) as i32;
+    let quarter = (0.25 * max_x as f32) as i32;
+    let (box_width, box_height) = if message_length + 3 <= quarter {
         (message_length + 1, 0)
     } else {
-        let width = (0.25 * max_x as f32) as u16;
+        let width = quarter.max(1);
         let height = message_length / width;
         (width, height)
     };
@@ -251,42 +208,23 @@ pub fn create_message_window(message: &str) -> Result<(), Box<dyn Error>> {
     // Calculate position based on terminal size
     let num_lines = box_height + 2;
     let num_cols = box_width + 4;
-    let border_x_start = (max_x as f32 * 0.7) as u16 - 2;
-    let border_y_start = (max_y as f32 * 0.7) as u16 - 1;
-    let message_x_start = (max_x as f32 * 0.7) as u16;
-    let message_y_start = (max_y as f32 * 0.7) as u16;
+    let border_x_start = ((max_x as f32 * 0.7) as i32 - 2).max(0);
+    let border_y_start = ((max_y as f32 * 0.7) as i32 - 1).max(0);
+    let message_x_start = (max_x as f32 * 0.7) as i32;
+    let message_y_start = (max_y as f32 * 0.7) as i32;
 
     // Draw the box border
-    queue!(
-        stdout,
-        MoveTo(border_x_start, border_y_start),
-        SetForegroundColor(Color::White),
-        SetBackgroundColor(Color::Reset),
-        Print("+"),
-        MoveTo(border_x_start + num_cols, border_y_start),
-        Print("+"),
-        MoveTo(border_x_start, border_y_start + num_lines),
-        Print("+"),
-        MoveTo(border_x_start + num_cols, border_y_start + num_lines),
-        Print("+"),
-    )?;
-    for i in 1..num_cols {
-        queue!(
-            stdout,
-            MoveTo(border_x_start + i, border_y_start),
-            Print("-"),
-            MoveTo(border_x_start + i, border_y_start + num_lines),
-            Print("-"),
-        )?;
-    }
+    let mut window = Vec::new();
+    let horizontal = format!("+{}+", "-".repeat(num_cols as usize - 1));
+    window.push((border_x_start, border_y_start, horizontal.clone()));
+    window.push((border_x_start, border_y_start + num_lines, horizontal));
     for i in 1..num_lines {
-        queue!(
-            stdout,
-            MoveTo(border_x_start, border_y_start + i),
-            Print("|"),
-            MoveTo(border_x_start + num_cols, border_y_start + i),
-            Print("|"),
-        )?;
+        window.push((border_x_start, border_y_start + i, "|".to_string()));
+        window.push((
+            border_x_start + num_cols,
+            border_y_start + i,
+            "|".to_string(),
+        ));
     }
 
     // Print the message inside the box
@@ -296,52 +234,153 @@ pub fn create_message_window(message: &str) -> Result<(), Box<dyn Error>> {
     let mut line_count = 0;
     for word in lines.iter() {
         if current_line.len() + word.len() > box_width as usize {
-            queue!(
-                stdout,
-                MoveTo(message_x_start + 1, message_y_start + line_count as u16),
-                Print(&current_line)
-            )?;
+            window.push((
+                message_x_start + 1,
+                message_y_start + line_count,
+                current_line.clone(),
+            ));
             current_line.clear();
             line_count += 1;
         }
         current_line.push_str(word);
         current_line.push(' ');
     }
-    queue!(
-        stdout,
-        MoveTo(message_x_start + 1, message_y_start + line_count as u16),
-        Print(&current_line)
-    )?;
+    window.push((
+        message_x_start + 1,
+        message_y_start + line_count,
+        current_line,
+    ));
+
+    window
+}
+
+/// The column `text` starts on and the part of it that fits on a screen
+/// `cols` wide when drawn from column `x`, or `None` if none of it does.
+/// Characters cut by an edge are left out, the same as
+/// [`Grid::put_str`](crate::render::Grid::put_str) does
+#[cfg(feature = "crossterm")]
+pub(crate) fn visible(x: i32, text: &str, cols: u16) -> Option<(u16, String)> {
+    let mut start = None;
+    let mut visible = String::new();
+    let mut x = x;
+    // whether the last character that takes up space was kept, so zero
+    // width characters go with it
+    let mut kept = false;
+    let mut join_next = false;
+    for c in text.chars() {
+        let width = c.width().unwrap_or(0) as i32;
+        if width == 0 || join_next {
+            if kept {
+                visible.push(c);
+            }
+            join_next = c == '\u{200d}';
+            continue;
+        }
+        kept = x >= 0 && x + width <= cols as i32;
+        if kept {
+            start.get_or_insert(x as u16);
+            visible.push(c);
+        }
+        x += width;
+    }
+    start.map(|start| (start, visible))
+}
+
+/// Style used for the message box
+pub fn message_style() -> Style {
+    Style {
+        attribute: Attribute::Reset,
+        foreground_color: Color::White,
+        background_color: Color::Reset,
+    }
+}
 
-    stdout.flush()?;
+#[cfg(feature = "crossterm")]
+pub fn create_message_window(
+    out: &mut impl Backend,
+    message: &str,
+    colors: ColorSupport,
+) -> Result<()> {
+    // Get terminal size
+    let (max_x, max_y) = out.size()?;
+
+    if colors.enabled() {
+        let style = message_style();
+        queue!(
+            out,
+            SetAttribute(style.attribute.into()),
+            SetForegroundColor(colors.adapt(style.foreground_color).into()),
+            SetBackgroundColor(colors.adapt(style.background_color).into()),
+        )?;
+    }
+    for (x, y, text) in message_window(message, max_x, max_y) {
+        if !(0..max_y as i32).contains(&y) {
+            continue;
+        }
+        if let Some((x, text)) = visible(x, &text, max_x) {
+            queue!(out, MoveTo(x, y as u16), Print(text))?;
+        }
+    }
+
+    out.flush()?;
 
     Ok(())
 }
 
-pub fn check_key_press() -> bool {
-    match event::poll(Duration::from_millis(0)) {
-        // if there was an error getting the event, we exit
-        // program so return true
-        Err(_) => true,
-        // if there wasn't an error getting the event
-        Ok(has_event) => {
-            // if no event, key not pressed
-            if !has_event {
-                return false;
-            }
-            match event::read() {
-                // if there was an error reading the event, exit program
-                Err(_) => true,
-                Ok(event) => {
-                    // otherwise, exit if event is key press
-                    if let Event::Key(key_event) = event {
-                        if let KeyEventKind::Press = key_event.kind {
-                            return true;
-                        }
-                    }
-                    false
+/// Something from the terminal that stops the tree from being drawn or shown
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    KeyPress,
+    /// The terminal changed to the given number of columns and rows
+    Resize(u16, u16),
+}
+
+/// Returns the key press or resize that happened since the last check, if
+/// any. Ctrl-C is reported as [`Error::Interrupted`] since raw mode stops it
+/// from sending SIGINT
+#[cfg(feature = "crossterm")]
+pub fn check_key_press() -> Result<Option<Input>> {
+    wait_for_input(Some(Duration::ZERO))
+}
+
+/// Wait for a key press or resize, giving up after `timeout`. Without a
+/// timeout this waits forever. Fails with [`Error::Signal`] if the process
+/// is asked to stop while waiting
+#[cfg(feature = "crossterm")]
+pub fn wait_for_input(timeout: Option<Duration>) -> Result<Option<Input>> {
+    // how long to wait for events before checking for signals again
+    const SIGNAL_CHECK: Duration = Duration::from_millis(50);
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        check_signal()?;
+        let remaining = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => SIGNAL_CHECK,
+        };
+        if event::poll(remaining.min(SIGNAL_CHECK)).map_err(Error::Terminal)? {
+            match event::read().map_err(Error::Terminal)? {
+                Event::Key(key_event) if is_key_press(key_event)? => {
+                    return Ok(Some(Input::KeyPress))
                 }
+                Event::Resize(cols, rows) => return Ok(Some(Input::Resize(cols, rows))),
+                _ => {}
             }
         }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(None);
+        }
+    }
+}
+
+/// Whether the key event should end drawing, failing on Ctrl-C
+#[cfg(feature = "crossterm")]
+pub fn is_key_press(key_event: KeyEvent) -> Result<bool> {
+    if key_event.kind != KeyEventKind::Press {
+        return Ok(false);
+    }
+    if key_event.modifiers.contains(KeyModifiers::CONTROL) && key_event.code == KeyCode::Char('c') {
+        return Err(Error::Interrupted);
     }
+    Ok(true)
 }
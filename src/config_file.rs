@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "cli")]
+use clap::{parser::ValueSource, ArgMatches};
+use serde::{de, Deserialize, Deserializer};
+
+#[cfg(feature = "cli")]
+use crate::{base::Base, lsystem::LSystem, palette, species, theme::Theme, Config};
+use crate::{
+    base::BaseSpec,
+    rng,
+    theme::{self, ColorChoice},
+    Error, Result,
+};
+
+/// Options that can be set in the config file, named like their flags.
+/// Anything left out keeps the command line default
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FileOptions {
+    pub live: Option<bool>,
+    pub time: Option<f64>,
+    pub infinite: Option<bool>,
+    pub wait: Option<f64>,
+    pub screensaver: Option<bool>,
+    pub message: Option<String>,
+    pub base: Option<BaseSpec>,
+    pub base_file: Option<PathBuf>,
+    pub multiplier: Option<i32>,
+    pub life: Option<i32>,
+    pub print: Option<bool>,
+    pub leaf: Option<Vec<String>>,
+    pub species: Option<String>,
+    pub lsystem: Option<PathBuf>,
+    #[serde(default, deserialize_with = "deserialize_seed")]
+    pub seed: Option<u64>,
+    pub verbose: Option<bool>,
+    /// A built-in theme, theme file, or theme from the `themes` directory
+    pub theme: Option<String>,
+    pub color: Option<ColorChoice>,
+    /// Overrides in the same `INDEX=#RRGGBB` form as the flag
+    pub export_color: Option<Vec<String>>,
+    pub export_background: Option<String>,
+}
+
+/// The contents of a config file: top level options, and named profiles that
+/// are applied on top of them
+#[derive(Clone, Debug, Default)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub options: FileOptions,
+    pub profiles: BTreeMap<String, FileOptions>,
+}
+
+/// Fill in every option not given on the command line from the config file
+/// and the selected profile, then from the species. The default config file
+/// is optional, but one named with `--config` must exist
+#[cfg(feature = "cli")]
+pub fn apply_config_file(config: &mut Config, matches: &ArgMatches) -> Result<()> {
+    let file = match &config.config {
+        Some(path) => Some(ConfigFile::read(path)?),
+        None => match default_path() {
+            Some(path) => ConfigFile::load(&path)?,
+            None => None,
+        },
+    };
+
+    let options = match file {
+        Some(file) => file.resolve(config.profile.as_deref())?,
+        None => match &config.profile {
+            Some(name) => {
+                return Err(Error::InvalidConfig(format!(
+                    "no config file to read profile `{}` from",
+                    name
+                )))
+            }
+            None => FileOptions::default(),
+        },
+    };
+    options.apply(config, matches)
+}
+
+/// Seeds are taken the same way as `--seed`, as a number or a string. TOML
+/// numbers can't hold seeds past `i64::MAX`, so those have to be strings
+fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seed {
+        Number(u64),
+        Text(String),
+    }
+
+    match Seed::deserialize(deserializer)? {
+        Seed::Number(seed) => Ok(Some(seed)),
+        Seed::Text(text) => rng::parse_seed(&text).map(Some).map_err(de::Error::custom),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/rbonsai`, falling back to `~/.config/rbonsai`
+pub fn config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("rbonsai"))
+}
+
+/// `config.toml` in the [`config_dir`]
+pub fn default_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+impl ConfigFile {
+    /// Read a config file
+    pub fn read(path: &Path) -> Result<ConfigFile> {
+        let text = fs::read_to_string(path).map_err(Error::file(path))?;
+        ConfigFile::parse(path, &text)
+    }
+
+    /// Read a config file. A missing file is not an error and gives `None`
+    pub fn load(path: &Path) -> Result<Option<ConfigFile>> {
+        match ConfigFile::read(path) {
+            Ok(file) => Ok(Some(file)),
+            Err(Error::File { source, .. }) if source.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Parse the text of a config file. Relative paths in it are taken to be
+    /// relative to `path`
+    pub fn parse(path: &Path, text: &str) -> Result<ConfigFile> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut table: toml::Table = text.parse().map_err(|err| Error::parse(path, err))?;
+
+        let mut profiles = BTreeMap::new();
+        if let Some(value) = table.remove("profile") {
+            let toml::Value::Table(sections) = value else {
+                return Err(Error::parse(path, "`profile` must be a table of profiles"));
+            };
+            for (name, section) in sections {
+                let options = FileOptions::deserialize(section)
+                    .map_err(|err| Error::parse(path, format!("[profile.{}]: {}", name, err)))?;
+                profiles.insert(name, options.relative_to(dir));
+            }
+        }
+        let options = FileOptions::deserialize(toml::Value::Table(table))
+            .map_err(|err| Error::parse(path, err))?
+            .relative_to(dir);
+
+        Ok(ConfigFile {
+            path: path.to_path_buf(),
+            options,
+            profiles,
+        })
+    }
+
+    /// The options of the file with the named profile applied on top
+    pub fn resolve(&self, profile: Option<&str>) -> Result<FileOptions> {
+        let Some(name) = profile else {
+            return Ok(self.options.clone());
+        };
+        let Some(overrides) = self.profiles.get(name) else {
+            return Err(Error::InvalidConfig(format!(
+                "no profile named `{}` in {}",
+                name,
+                self.path.display()
+            )));
+        };
+        Ok(self.options.clone().merge(overrides.clone()))
+    }
+}
+
+impl FileOptions {
+    /// Join relative paths onto `dir`, the directory of the config file
+    fn relative_to(mut self, dir: &Path) -> FileOptions {
+        self.base_file = self.base_file.map(|path| dir.join(path));
+        self.lsystem = self.lsystem.map(|path| dir.join(path));
+        self.theme = self.theme.map(|theme| match theme::is_file_name(&theme) {
+            true => dir.join(theme).to_string_lossy().into_owned(),
+            false => theme,
+        });
+        self
+    }
+
+    /// Combine two sets of options, preferring the ones set in `other`. A
+    /// base or species in `other` also replaces a base file or L-system in
+    /// `self`, which would otherwise be used instead of them
+    pub fn merge(self, other: FileOptions) -> FileOptions {
+        let base_file = match other.base {
+            Some(_) => other.base_file,
+            None => other.base_file.or(self.base_file),
+        };
+        let lsystem = match other.species {
+            Some(_) => other.lsystem,
+            None => other.lsystem.or(self.lsystem),
+        };
+        FileOptions {
+            live: other.live.or(self.live),
+            time: other.time.or(self.time),
+            infinite: other.infinite.or(self.infinite),
+            wait: other.wait.or(self.wait),
+            screensaver: other.screensaver.or(self.screensaver),
+            message: other.message.or(self.message),
+            base: other.base.or(self.base),
+            base_file,
+            multiplier: other.multiplier.or(self.multiplier),
+            life: other.life.or(self.life),
+            print: other.print.or(self.print),
+            leaf: other.leaf.or(self.leaf),
+            species: other.species.or(self.species),
+            lsystem,
+            seed: other.seed.or(self.seed),
+            verbose: other.verbose.or(self.verbose),
+            theme: other.theme.or(self.theme),
+            color: other.color.or(self.color),
+            export_color: other.export_color.or(self.export_color),
+            export_background: other.export_background.or(self.export_background),
+        }
+    }
+
+    /// Set every option in `config` that wasn't given on the command line
+    #[cfg(feature = "cli")]
+    pub fn apply(self, config: &mut Config, matches: &ArgMatches) -> Result<()> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        macro_rules! set {
+            ($($field:ident),*) => {$(
+                if let Some(value) = self.$field {
+                    if !from_cli(stringify!($field)) {
+                        config.$field = value;
+                    }
+                }
+            )*};
+        }
+        set!(
+            live,
+            time,
+            infinite,
+            wait,
+            screensaver,
+            multiplier,
+            life,
+            print,
+            verbose,
+            color
+        );
+
+        if !from_cli("message") && self.message.is_some() {
+            config.message = self.message;
+        }
+        if !from_cli("seed") && self.seed.is_some() {
+            config.seed = self.seed;
+        }
+        if let (false, Some(leaves)) = (from_cli("leaves"), self.leaf) {
+            config.leaves = Some(leaves);
+        }
+        if let (false, Some(species)) = (from_cli("species"), self.species) {
+            config.species = species::parse_species_name(&species).map_err(Error::InvalidConfig)?;
+        }
+        if let (false, Some(base)) = (from_cli("base"), self.base) {
+            config.base = base.name().map_err(Error::InvalidConfig)?;
+        }
+        // a base or species given on the command line beats a base file or
+        // L-system from the file, which would otherwise be used instead
+        if let (false, Some(path)) = (from_cli("base_file") || from_cli("base"), self.base_file) {
+            config.base_file = Some(Base::read(&path)?);
+        }
+        if let (false, Some(path)) = (from_cli("lsystem") || from_cli("species"), self.lsystem) {
+            config.lsystem = Some(LSystem::read(&path)?);
+        }
+        if let (false, Some(theme)) = (from_cli("theme"), self.theme) {
+            config.theme = Some(Theme::load(&theme)?);
+        }
+        if let (false, Some(colors)) = (from_cli("export_color"), self.export_color) {
+            config.export_color = colors
+                .iter()
+                .map(|color| palette::parse_color_override(color))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|err| Error::InvalidConfig(format!("export-color: {}", err)))?;
+        }
+        if let (false, Some(background)) = (from_cli("export_background"), self.export_background) {
+            config.export_background = Some(
+                palette::parse_hex(&background)
+                    .map_err(|err| Error::InvalidConfig(format!("export-background: {}", err)))?,
+            );
+        }
+        Ok(())
+    }
+}
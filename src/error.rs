@@ -0,0 +1,87 @@
+use std::{fmt, io, path::PathBuf};
+
+/// Everything that can go wrong while growing, drawing or exporting a tree
+#[derive(Debug)]
+pub enum Error {
+    /// Writing the output failed
+    Io(io::Error),
+    /// There is no terminal to draw on, or it couldn't be set up or read
+    /// from
+    Terminal(io::Error),
+    /// Reading or writing a file failed
+    File { path: PathBuf, source: io::Error },
+    /// The terminal is smaller than what has to be drawn
+    TerminalTooSmall {
+        width: u16,
+        height: u16,
+        min_width: u16,
+        min_height: u16,
+    },
+    /// An option is out of range or conflicts with another option
+    InvalidConfig(String),
+    /// A file has contents that could not be understood
+    Parse { path: PathBuf, message: String },
+    /// Encoding an exported image failed
+    Encoding(String),
+    /// The user pressed Ctrl-C
+    Interrupted,
+    /// The process was asked to stop by the given signal
+    Signal(i32),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Attach `path` to an I/O error, for use with `map_err`
+    pub fn file(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::File { path, source }
+    }
+
+    pub(crate) fn parse(path: impl Into<PathBuf>, message: impl fmt::Display) -> Error {
+        Error::Parse {
+            path: path.into(),
+            message: message.to_string().trim_end().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "could not write output: {}", err),
+            Error::Terminal(err) => write!(f, "could not use the terminal: {}", err),
+            Error::File { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::TerminalTooSmall {
+                width,
+                height,
+                min_width,
+                min_height,
+            } => write!(
+                f,
+                "terminal is too small ({}x{}), need at least {}x{}",
+                width, height, min_width, min_height
+            ),
+            Error::InvalidConfig(message) => write!(f, "invalid option: {}", message),
+            Error::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::Encoding(message) => write!(f, "could not encode image: {}", message),
+            Error::Interrupted => write!(f, "interrupted"),
+            Error::Signal(signal) => write!(f, "stopped by signal {}", signal),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) | Error::Terminal(err) | Error::File { source: err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
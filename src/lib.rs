@@ -1,10 +1,40 @@
-use clap::Parser;
+#[cfg(feature = "cli")]
+use std::{io::IsTerminal, path::PathBuf};
 
+#[cfg(feature = "cli")]
+use clap::{ArgGroup, Parser};
+
+pub mod animation;
+#[cfg(feature = "crossterm")]
+pub mod backend;
 pub mod base;
 pub mod bonsai;
+pub mod config_file;
+pub mod error;
+pub mod font;
+pub mod lsystem;
+pub mod palette;
+pub mod record;
+pub mod render;
+pub mod rng;
+pub mod save;
+pub mod species;
+pub mod style;
+pub mod svg;
+#[cfg(feature = "crossterm")]
+pub mod terminal;
+pub mod theme;
+pub mod tree;
+#[cfg(feature = "ratatui")]
+pub mod widget;
+
+pub use error::{Error, Result};
 
+/// The command line options
+#[cfg(feature = "cli")]
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(group(ArgGroup::new("output").multiple(true).args(["print", "export_svg", "export_gif"])))]
 pub struct Config {
     /// Whether the tree generation should pause after each step
     /// to allow the user to watch it grow
@@ -25,9 +55,18 @@ pub struct Config {
     /// Attach message next to tree
     #[arg(short, long)]
     pub message: Option<String>,
-    /// Ascii art plant base to use.
-    #[arg(short, long, default_value_t = 1)]
-    pub base: u8,
+    /// Ascii art plant base to use, by name or number. See --list-bases
+    #[arg(
+        short,
+        long,
+        value_name = "NAME",
+        default_value = "large",
+        value_parser = base::parse_base_name
+    )]
+    pub base: String,
+    /// Load the plant base from a file instead
+    #[arg(long, value_name = "FILE", value_parser = base::parse_base_file)]
+    pub base_file: Option<base::Base>,
     /// The branch multiplier; higher -> less branches
     #[arg(short = 'M', long, default_value_t = 3)]
     pub multiplier: i32,
@@ -38,11 +77,158 @@ pub struct Config {
     /// Print tree to terminal when finished
     #[arg(short, long, default_value_t = false)]
     pub print: bool,
-    /// Random number seed for reproducable trees
-    #[arg(short, long)]
+    /// Width of the printed or exported tree. Printing with a fixed size
+    /// writes straight to stdout instead of the alternate screen
+    #[arg(long, requires = "output")]
+    pub width: Option<u16>,
+    /// Height of the printed or exported tree, including the base
+    #[arg(long, requires = "output")]
+    pub height: Option<u16>,
+    /// Comma-separated list of strings randomly chosen for leaves
+    /// [default: the species' leaves, or &]
+    #[arg(short = 'c', long = "leaf", value_name = "LIST", value_delimiter = ',')]
+    pub leaves: Option<Vec<String>>,
+    /// Shape of the tree: default, juniper, weeping-willow, pine, maple,
+    /// cascade or broom. Brings its own leaves and colors unless --leaf or
+    /// --theme is given
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = "default",
+        value_parser = species::parse_species_name
+    )]
+    pub species: String,
+    /// Draw the tree from an L-system grammar file instead of growing it
+    #[arg(long, value_name = "FILE", value_parser = lsystem::parse_lsystem_file)]
+    pub lsystem: Option<lsystem::LSystem>,
+    /// Random number seed for reproducable trees, as a number or
+    /// GENERATION:SEED
+    #[arg(short, long, value_parser = rng::parse_seed)]
     pub seed: Option<u64>,
+    /// Color theme: default, autumn, cherry-blossom, winter, monochrome, or a
+    /// theme file [default: default, with the species' tree colors]
+    #[arg(long, value_name = "NAME|FILE", value_parser = theme::parse_theme)]
+    pub theme: Option<theme::Theme>,
+    /// When to use color. Auto uses color on a terminal unless NO_COLOR is
+    /// set
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    pub color: theme::ColorChoice,
+    /// Don't use color, same as --color=never
+    #[arg(long, conflicts_with = "color")]
+    pub no_color: bool,
+    /// Show every built-in base and exit
+    #[arg(long)]
+    pub list_bases: bool,
     /// Whether there should be debug prints
     #[arg(short, long, default_value_t = false)]
     pub verbose: bool,
-    // TODO: Add support for saving to file, loading from file
+    /// Read defaults from this file instead of
+    /// $XDG_CONFIG_HOME/rbonsai/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Apply a [profile.NAME] section of the config file
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Save the generated tree to a file
+    #[arg(long, value_name = "PATH")]
+    pub save: Option<PathBuf>,
+    /// Load a previously saved tree from a file instead of growing one
+    #[arg(long, value_name = "PATH", conflicts_with = "save")]
+    pub load: Option<PathBuf>,
+    /// Record the terminal output as an asciicast v2 file for asciinema
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+    /// Export the tree as an SVG image
+    #[arg(long, value_name = "FILE")]
+    pub export_svg: Option<PathBuf>,
+    /// Export the growth of the tree as an animated GIF, with --time between
+    /// steps and --wait on the finished tree
+    #[arg(long, value_name = "FILE")]
+    pub export_gif: Option<PathBuf>,
+    /// Override one of the 16 ANSI colors in exported images, e.g. 2=#4e9a06
+    #[arg(long, value_name = "INDEX=#RRGGBB", value_parser = palette::parse_color_override)]
+    pub export_color: Vec<(u8, String)>,
+    /// Background color of exported images
+    #[arg(long, value_name = "#RRGGBB", value_parser = palette::parse_hex)]
+    pub export_background: Option<String>,
+}
+
+#[cfg(feature = "cli")]
+impl Config {
+    /// The plant base to draw, or `None` for no base
+    pub fn base(&self) -> Option<base::Base> {
+        match &self.base_file {
+            Some(base) => Some(base.clone()),
+            None => base::Base::named(&self.base),
+        }
+    }
+
+    /// The species to grow
+    pub fn species(&self) -> species::Species {
+        species::Species::builtin(&self.species).unwrap_or_default()
+    }
+
+    /// The leaves to draw, from `--leaf` or else the species
+    pub fn leaves(&self) -> Vec<String> {
+        match &self.leaves {
+            Some(leaves) => leaves.clone(),
+            None => self
+                .species()
+                .leaves
+                .unwrap_or_else(|| bonsai::GrowthParams::default().leaves),
+        }
+    }
+
+    /// The theme to draw with, from `--theme` or else the default theme with
+    /// the species' tree colors
+    pub fn theme(&self) -> theme::Theme {
+        match &self.theme {
+            Some(theme) => theme.clone(),
+            None => {
+                let mut theme = theme::Theme::default();
+                if let Some(colors) = self.species().colors {
+                    theme.tree = colors;
+                }
+                theme
+            }
+        }
+    }
+
+    /// The plant base colored with the theme
+    pub fn base_art(&self) -> Option<base::BaseArt> {
+        self.base().map(|base| base.art(&self.theme().base))
+    }
+
+    /// The colors to draw with on stdout
+    pub fn color_support(&self) -> theme::ColorSupport {
+        let choice = if self.no_color {
+            theme::ColorChoice::Never
+        } else {
+            self.color
+        };
+        choice.resolve(std::io::stdout().is_terminal())
+    }
+
+    /// Check the options that clap can't check on its own
+    pub fn validate(&self) -> Result<()> {
+        bonsai::GrowthParams::from_config(self).validate()?;
+        if !(self.time >= 0. && self.time.is_finite()) {
+            return Err(Error::InvalidConfig(format!(
+                "--time must not be negative, got {}",
+                self.time
+            )));
+        }
+        if !(self.wait >= 0. && self.wait.is_finite()) {
+            return Err(Error::InvalidConfig(format!(
+                "--wait must not be negative, got {}",
+                self.wait
+            )));
+        }
+        if self.width == Some(0) || self.height == Some(0) {
+            return Err(Error::InvalidConfig(
+                "--width and --height must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
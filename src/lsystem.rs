@@ -0,0 +1,362 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bonsai::{
+        utility::{choose_color, choose_string},
+        BranchType, Canvas, GrowthParams, Position, Val,
+    },
+    rng::TreeRng,
+    Error, Result,
+};
+
+/// Expansion stops before the symbols grow past this many, so a grammar with
+/// too many iterations can't use up all the memory
+pub const MAX_SYMBOLS: usize = 200_000;
+
+/// A grammar that is rewritten a number of times and then drawn by a turtle
+/// that starts at the base facing up
+///
+/// `F` and `G` draw a step forward, `f` moves a step without drawing, `+`
+/// and `-` turn left and right by `angle`, `|` turns around, `[` and `]`
+/// save and restore the position and heading, and `L` draws a leaf. Leaves
+/// are also drawn at the end of every branch closed by `]`. Anything else
+/// is only used by the rules
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LSystem {
+    pub axiom: String,
+    /// What each symbol is replaced with on every iteration. Symbols without
+    /// a rule are kept as they are
+    pub rules: BTreeMap<char, String>,
+    /// Degrees turned by `+` and `-`
+    pub angle: f64,
+    pub iterations: u32,
+}
+
+/// Something the turtle drew, in turtle units with y going up
+enum Mark {
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        heading: f64,
+        depth: usize,
+    },
+    Leaf((f64, f64)),
+}
+
+impl LSystem {
+    /// Read a grammar file
+    pub fn read(path: &Path) -> Result<LSystem> {
+        let text = fs::read_to_string(path).map_err(Error::file(path))?;
+        LSystem::parse(path, &text)
+    }
+
+    /// Parse the text of a grammar file. `path` is only used in errors
+    ///
+    /// Each line is a setting, `axiom = X`, `angle = 25` or
+    /// `iterations = 5`, or a rule, `X -> F[+X]F[-X]+X`. Blank lines and
+    /// lines starting with `#` are ignored
+    pub fn parse(path: &Path, text: &str) -> Result<LSystem> {
+        let mut axiom = None;
+        let mut angle = 25.0;
+        let mut iterations = 4;
+        let mut rules = BTreeMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error =
+                |message: String| Error::parse(path, format!("line {}: {}", number + 1, message));
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((symbol, replacement)) = line.split_once("->") {
+                let mut chars = symbol.trim().chars();
+                let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                    return Err(error(format!(
+                        "rules replace a single symbol, not `{}`",
+                        symbol.trim()
+                    )));
+                };
+                rules.insert(symbol, strip_spaces(replacement));
+            } else if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim() {
+                    "axiom" => axiom = Some(strip_spaces(value)),
+                    "angle" => {
+                        angle = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|angle| angle.is_finite())
+                            .ok_or_else(|| error(format!("`{}` is not an angle", value)))?;
+                    }
+                    "iterations" => {
+                        iterations = value.parse().map_err(|_| {
+                            error(format!("`{}` is not a number of iterations", value))
+                        })?;
+                    }
+                    key => {
+                        return Err(error(format!(
+                            "unknown setting `{}`, use axiom, angle or iterations",
+                            key
+                        )))
+                    }
+                }
+            } else {
+                return Err(error(format!(
+                    "expected `setting = value` or `symbol -> replacement`, got `{}`",
+                    line
+                )));
+            }
+        }
+
+        let Some(axiom) = axiom.filter(|axiom| !axiom.is_empty()) else {
+            return Err(Error::parse(path, "missing `axiom`"));
+        };
+        let lsystem = LSystem {
+            axiom,
+            rules,
+            angle,
+            iterations,
+        };
+        if lsystem.expand().chars().count() > MAX_SYMBOLS {
+            return Err(Error::parse(
+                path,
+                format!("grows past {} symbols, use fewer iterations", MAX_SYMBOLS),
+            ));
+        }
+        Ok(lsystem)
+    }
+
+    /// The axiom after applying the rules `iterations` times. Stops as soon
+    /// as it is longer than [`MAX_SYMBOLS`], cutting it off there
+    pub fn expand(&self) -> String {
+        let mut symbols = self.axiom.clone();
+        let mut count = symbols.chars().count();
+        for _ in 0..self.iterations {
+            if count > MAX_SYMBOLS {
+                break;
+            }
+            let mut next = String::new();
+            count = 0;
+            for symbol in symbols.chars() {
+                match self.rules.get(&symbol) {
+                    Some(replacement) => {
+                        next.push_str(replacement);
+                        count += replacement.chars().count();
+                    }
+                    None => {
+                        next.push(symbol);
+                        count += 1;
+                    }
+                }
+                if count > MAX_SYMBOLS {
+                    return next;
+                }
+            }
+            symbols = next;
+        }
+        symbols
+    }
+
+    /// Walk the turtle over the expanded symbols
+    fn marks(&self) -> Vec<Mark> {
+        let mut marks = Vec::new();
+        let (mut x, mut y, mut heading) = (0.0f64, 0.0f64, 90.0f64);
+        // position, heading and whether anything was drawn since the push
+        let mut stack: Vec<(f64, f64, f64, bool)> = Vec::new();
+        let mut drawn = false;
+
+        for symbol in self.expand().chars() {
+            match symbol {
+                'F' | 'G' | 'f' => {
+                    let (sin, cos) = heading.to_radians().sin_cos();
+                    let to = (quantize(x + cos), quantize(y + sin));
+                    if symbol != 'f' {
+                        marks.push(Mark::Line {
+                            from: (x, y),
+                            to,
+                            heading,
+                            depth: stack.len(),
+                        });
+                        drawn = true;
+                    }
+                    (x, y) = to;
+                }
+                '+' => heading += self.angle,
+                '-' => heading -= self.angle,
+                '|' => heading += 180.0,
+                '[' => {
+                    stack.push((x, y, heading, drawn));
+                    drawn = false;
+                }
+                ']' => {
+                    if drawn {
+                        marks.push(Mark::Leaf((x, y)));
+                    }
+                    if let Some(saved) = stack.pop() {
+                        (x, y, heading, drawn) = saved;
+                    }
+                }
+                'L' => marks.push(Mark::Leaf((x, y))),
+                _ => {}
+            }
+        }
+        marks
+    }
+
+    /// Draw the grammar on the canvas, scaled to fill it above the base.
+    /// Cells are drawn in the order the turtle visits them, and the rng is
+    /// only used for leaves and colors
+    pub fn grow(&self, params: &GrowthParams, rng: &mut TreeRng, canvas: Canvas) -> Vec<Val> {
+        let marks = self.marks();
+        let max_y = canvas.ground();
+
+        // the turtle starts at the bottom middle of the canvas. Cells are
+        // about twice as tall as they are wide, so x is stretched to match
+        let (mut left, mut right, mut top) = (0.0f64, 0.0f64, 0.0f64);
+        for mark in &marks {
+            let points = match mark {
+                Mark::Line { from, to, .. } => vec![*from, *to],
+                Mark::Leaf(at) => vec![*at],
+            };
+            for (x, y) in points {
+                left = left.min(x);
+                right = right.max(x);
+                top = top.max(y);
+            }
+        }
+        let half_width = (canvas.width / 2).saturating_sub(1) as f64;
+        let mut scale = f64::INFINITY;
+        if top > 0.0 {
+            scale = scale.min(max_y.saturating_sub(1) as f64 / top);
+        }
+        if right.max(-left) > 0.0 {
+            scale = scale.min(half_width / (2.0 * right.max(-left)));
+        }
+        if !scale.is_finite() {
+            scale = 1.0;
+        }
+        let origin = ((canvas.width / 2) as f64, max_y as f64);
+        let cell = |(x, y): (f64, f64)| Position {
+            x: (origin.0 + x * 2.0 * scale).round() as i32,
+            y: (origin.1 - y * scale).round() as i32,
+        };
+
+        let mut steps = Vec::new();
+        let mut started = false;
+        for mark in marks {
+            match mark {
+                Mark::Line {
+                    from,
+                    to,
+                    heading,
+                    depth,
+                } => {
+                    let branch_type = match depth {
+                        0 => BranchType::Trunk,
+                        _ if heading.to_radians().cos() < 0.0 => BranchType::ShootLeft,
+                        _ => BranchType::ShootRight,
+                    };
+                    let glyph = line_glyph(heading);
+                    // the trunk starts on the ground, every other line
+                    // carries on from a cell that is already drawn
+                    if !started {
+                        steps.push((cell(from), 0, 0, branch_type, Some(glyph)));
+                        started = true;
+                    }
+                    for (pos, dx, dy) in line(cell(from), cell(to)) {
+                        steps.push((pos, dx, dy, branch_type, Some(glyph)));
+                    }
+                }
+                Mark::Leaf(at) => {
+                    let branch_type = if rng.one_in(2) {
+                        BranchType::Dying
+                    } else {
+                        BranchType::Dead
+                    };
+                    steps.push((cell(at), 0, 0, branch_type, None));
+                }
+            }
+        }
+
+        let total = steps.len() as i32;
+        let mut tree = Vec::new();
+        for (i, (pos, dx, dy, branch_type, glyph)) in steps.into_iter().enumerate() {
+            if pos.x < 0 || pos.x >= canvas.width as i32 || pos.y < 0 || pos.y > max_y as i32 {
+                continue;
+            }
+            let char = match glyph {
+                Some(glyph) => glyph.to_string(),
+                None => choose_string(&params.leaves, &branch_type, 0, dx, dy, rng),
+            };
+            tree.push(Val {
+                style: choose_color(&branch_type, &params.colors, rng),
+                char,
+                pos,
+                dx,
+                dy,
+                life: total - i as i32,
+                branch_type,
+                shoots: 0,
+                shoot_cooldown: 0,
+            });
+        }
+        tree
+    }
+}
+
+/// Parse a `--lsystem` argument
+pub fn parse_lsystem_file(path: &str) -> Result<LSystem, String> {
+    LSystem::read(Path::new(path)).map_err(|err| err.to_string())
+}
+
+/// Round away the last few bits, which `sin` and `cos` don't promise to get
+/// the same on every platform, so the same grammar draws the same cells
+/// everywhere
+fn quantize(value: f64) -> f64 {
+    (value * 1e9).round() / 1e9
+}
+
+fn strip_spaces(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// The character for a line going in the given direction, in degrees
+/// counterclockwise from pointing right
+fn line_glyph(heading: f64) -> &'static str {
+    match heading.rem_euclid(180.0) {
+        angle if angle < 22.5 => "_",
+        angle if angle < 67.5 => "/",
+        angle if angle < 112.5 => "|",
+        angle if angle < 157.5 => "\\",
+        _ => "_",
+    }
+}
+
+/// The cells on the line between two cells, leaving out the first, along
+/// with the step taken to reach each one
+fn line(from: Position, to: Position) -> Vec<(Position, i32, i32)> {
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let mut pos = from;
+    let mut cells = Vec::new();
+    while pos != to {
+        let (mut moved_x, mut moved_y) = (0, 0);
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            pos.x += step_x;
+            moved_x = step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            pos.y += step_y;
+            moved_y = step_y;
+        }
+        cells.push((pos, moved_x, moved_y));
+    }
+    cells
+}
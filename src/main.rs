@@ -1,121 +1,340 @@
 use std::{
-    io::stdout,
-    thread,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    fs,
+    io::{self, stdout, IsTerminal, Write},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
 use crossterm::{
-    cursor::{self, MoveTo},
-    event::{self, Event, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    cursor::MoveTo,
+    event, execute,
+    style::Print,
+    terminal::{self, Clear, ClearType},
 };
-use rand::{rngs::StdRng, SeedableRng};
 use rbonsai::{
+    animation::{write_gif, GifOptions},
+    backend::{Backend, CrosstermBackend},
+    base::{Base, BUILTIN_BASES},
     bonsai::{
         draw_tree, grow_tree, init,
-        utility::{check_key_press, create_message_window},
+        utility::{create_message_window, Input},
+        Canvas, Val,
     },
-    Config,
+    config_file::apply_config_file,
+    palette::Palette,
+    record::{Recorder, Tee},
+    render::{render, Grid},
+    rng::{seed_string, TreeRng},
+    save::{load_tree, save_tree, SavedTree},
+    svg::to_svg,
+    terminal::{install_panic_hook, TerminalGuard},
+    Config, Error, Result,
 };
+use unicode_width::UnicodeWidthStr;
 
 fn main() {
-    let mut args = Config::parse();
+    let matches = Config::command().get_matches();
+    let args = Config::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    match run(args, &matches) {
+        Ok(()) => {}
+        // Ctrl-C, exit the way the shell expects from SIGINT
+        Err(Error::Interrupted) => process::exit(130),
+        Err(Error::Signal(signal)) => process::exit(128 + signal),
+        // the reader went away, as with `| head`, exit the way the shell
+        // expects from SIGPIPE
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => process::exit(141),
+        Err(err) => {
+            eprintln!("rbonsai: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the name of every built-in base with a preview
+fn list_bases(args: &Config) -> Result<()> {
+    let mut out = stdout().lock();
+    for name in BUILTIN_BASES {
+        let number = match name {
+            "none" => " (0)",
+            "large" => " (1)",
+            "small" => " (2)",
+            _ => "",
+        };
+        writeln!(out, "{}{}", name, number)?;
+        let Some(art) = Base::named(name).map(|base| base.art(&args.theme().base)) else {
+            writeln!(out)?;
+            continue;
+        };
+        let mut grid = Grid::new(art.width, art.lines.len() as u16);
+        for (y, line) in art.lines.iter().enumerate() {
+            let mut x = 0;
+            for span in line {
+                grid.put_str(x, y as i32, &span.text, &span.style);
+                x += span.text.width() as i32;
+            }
+        }
+        writeln!(out, "{}", grid.to_ansi_string(args.color_support()))?;
+    }
+    Ok(())
+}
+
+fn run(mut args: Config, matches: &ArgMatches) -> Result<()> {
+    apply_config_file(&mut args, matches)?;
+    args.validate()?;
+
+    if args.list_bases {
+        return list_bases(&args);
+    }
 
     if args.screensaver {
         args.live = true;
         args.infinite = true;
     }
 
-    let mut stdout = stdout();
+    let stdout = stdout();
+
+    let mut loaded = args.load.as_deref().map(load_tree).transpose()?;
+
+    // a loaded tree must be drawn with the parameters it was grown with
+    if let Some(saved) = &loaded {
+        saved.apply_to(&mut args);
+    }
 
     let seed = args.seed.unwrap_or_else(|| {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .expect("time went backwards")
-            .as_secs()
+            .map_or(0, |time| time.as_secs())
     });
 
-    let mut rng = StdRng::seed_from_u64(seed);
+    let mut rng = TreeRng::new(seed);
 
-    enable_raw_mode().unwrap();
-    execute!(stdout, cursor::Hide).unwrap();
-    execute!(stdout, EnterAlternateScreen).unwrap();
+    // without a terminal to draw on, when asked for a fixed size or when
+    // exporting, render the finished tree without touching the terminal
+    let fixed_size = args.width.is_some() || args.height.is_some();
+    if args.export_svg.is_some()
+        || args.export_gif.is_some()
+        || (args.print && (fixed_size || !stdout.is_terminal()))
+    {
+        return render_static(&args, seed, &mut rng, &mut loaded);
+    }
+
+    let recorder = match &args.record {
+        Some(path) => {
+            let (cols, rows) = terminal::size().map_err(Error::Terminal)?;
+            let file = fs::File::create(path).map_err(Error::file(path))?;
+            let recorder =
+                Recorder::new(io::BufWriter::new(file), cols, rows).map_err(Error::file(path))?;
+            Some(recorder)
+        }
+        None => None,
+    };
+    let mut stdout = CrosstermBackend::new(Tee::new(stdout, recorder));
+
+    install_panic_hook();
+    #[cfg(unix)]
+    rbonsai::terminal::handle_signals()?;
+    let guard = TerminalGuard::new(&mut stdout)?;
 
     // Flush any pending events
-    while event::poll(Duration::from_millis(10)).unwrap() {
+    while event::poll(Duration::from_millis(10)).map_err(Error::Terminal)? {
         let _ = event::read();
     }
 
-    let mut should_exit: bool;
+    let mut should_exit = false;
+    // the rng as it was before growing the current tree, so a resize can
+    // regrow the same tree for the new size
+    let mut tree_rng = rng.clone();
+    let mut resized = false;
+    let mut tree = Vec::new();
 
-    let last_tree = loop {
-        init(&args);
-        let tree = grow_tree(&args, &mut rng);
+    loop {
+        if resized {
+            rng = tree_rng.clone();
+        } else {
+            tree_rng = rng.clone();
+        }
+
+        match init(&mut stdout, &args) {
+            Ok(()) => resized = false,
+            // the terminal may only be small for a moment while resizing, so
+            // wait for it to grow again instead of exiting
+            Err(Error::TerminalTooSmall { .. }) if resized => {
+                execute!(stdout, Clear(ClearType::All))?;
+                match stdout.wait_for_input(None)? {
+                    Some(Input::KeyPress) => {
+                        should_exit = true;
+                        break;
+                    }
+                    Some(Input::Resize(cols, rows)) => stdout.get_mut().resize(cols, rows)?,
+                    None => {}
+                }
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+        let (cols, rows) = stdout.size()?;
+        if args.verbose {
+            let canvas = Canvas::new(cols, rows, args.base().as_ref());
+            execute!(
+                stdout,
+                MoveTo(5, 1),
+                Print(format!("seed: {}", seed_string(seed))),
+                MoveTo(5, 2),
+                Print(format!(
+                    "maxX: {:03}, maxY: {:03}",
+                    canvas.width,
+                    canvas.ground()
+                )),
+            )?;
+        }
+        tree = next_tree(&args, seed, &mut rng, &mut loaded, (cols, rows))?;
 
         // if the user exited before the tree is finished being drawn, should
         // exit program
-        should_exit = !draw_tree(&args, &tree);
-
-        if should_exit {
-            break tree;
-        }
+        let mut input = draw_tree(&mut stdout, &args, &tree)?;
 
-        if let Some(message) = &args.message {
-            create_message_window(message).unwrap();
-        }
+        if input.is_none() {
+            if let Some(message) = &args.message {
+                create_message_window(&mut stdout, message, args.color_support())?;
+            }
 
-        if !args.infinite {
-            break tree;
-        }
-        let start = Instant::now();
-        let mut finished = false;
-        while start.elapsed() < Duration::from_secs_f64(args.wait) {
-            if check_key_press() {
-                finished = true;
+            if args.infinite {
+                input = stdout.wait_for_input(Some(Duration::from_secs_f64(args.wait)))?;
+            } else if args.print {
                 break;
+            } else {
+                input = stdout.wait_for_input(None)?;
             }
-            thread::sleep(Duration::from_millis(50)); // Sleep to avoid busy-waiting
         }
 
-        if finished {
-            should_exit = true;
-            break tree;
+        match input {
+            Some(Input::KeyPress) => {
+                should_exit = true;
+                break;
+            }
+            // start over on the resized terminal with the same tree
+            Some(Input::Resize(cols, rows)) => {
+                stdout.get_mut().resize(cols, rows)?;
+                resized = true;
+            }
+            None => {}
         }
-    };
+    }
 
-    let (_, rows) = crossterm::terminal::size().unwrap();
+    let (_, rows) = stdout.size()?;
+    let last_row = rows.saturating_sub(1);
     if should_exit {
-        execute!(stdout, LeaveAlternateScreen).unwrap();
-    } else if args.print {
+        guard.leave_alternate_screen(&mut stdout)?;
+    } else {
         args.live = false;
-        execute!(stdout, LeaveAlternateScreen).unwrap();
+        guard.leave_alternate_screen(&mut stdout)?;
         for _ in 0..rows {
-            println!();
+            writeln!(stdout)?;
         }
 
-        init(&args);
-        draw_tree(&args, &last_tree);
+        init(&mut stdout, &args)?;
+        draw_tree(&mut stdout, &args, &tree)?;
         if let Some(message) = &args.message {
-            create_message_window(message).unwrap();
+            create_message_window(&mut stdout, message, args.color_support())?;
         }
-        execute!(stdout, MoveTo(0, rows - 1),).unwrap();
-        println!();
-    } else {
-        while let Ok(event) = event::read() {
-            // break if key event
-            if let Event::Key(key_event) = event {
-                if let KeyEventKind::Press = key_event.kind {
-                    break;
-                }
-            }
-        }
-        execute!(stdout, LeaveAlternateScreen).unwrap();
+        execute!(stdout, MoveTo(0, last_row),)?;
+        writeln!(stdout)?;
     }
 
     // move cursor to bottom of terminal
-    execute!(stdout, MoveTo(0, rows - 1),).unwrap();
-    let _ = disable_raw_mode();
-    execute!(stdout, cursor::Show).unwrap();
+    execute!(stdout, MoveTo(0, last_row),)?;
+
+    Ok(())
+}
+
+/// Grow a new tree for a canvas of the given size, or fit the loaded tree to
+/// it. Grown trees are saved if requested
+fn next_tree(
+    args: &Config,
+    seed: u64,
+    rng: &mut TreeRng,
+    loaded: &mut Option<SavedTree>,
+    size: (u16, u16),
+) -> Result<Vec<Val>> {
+    if let Some(saved) = loaded {
+        saved.fit_to(size);
+        return Ok(saved.tree.clone());
+    }
+
+    let canvas = Canvas::new(size.0, size.1, args.base().as_ref());
+    let tree = grow_tree(args, rng, canvas);
+    if let Some(path) = &args.save {
+        let saved = SavedTree::new(args, seed, size, tree.clone());
+        save_tree(path, &saved)?;
+    }
+    Ok(tree)
+}
+
+/// Print and/or export a finished tree without touching the terminal state
+fn render_static(
+    args: &Config,
+    seed: u64,
+    rng: &mut TreeRng,
+    loaded: &mut Option<SavedTree>,
+) -> Result<()> {
+    // fall back to a standard terminal size when stdout isn't one
+    let (cols, rows) = terminal::size().unwrap_or((80, 24));
+    let size = (args.width.unwrap_or(cols), args.height.unwrap_or(rows));
+    // the same as drawing on a terminal this size, rather than cropping
+    // the base
+    let base = args.base_art();
+    if let Some(art) = &base {
+        art.check_fits(size.0, size.1)?;
+    }
+
+    let tree = next_tree(args, seed, rng, loaded, size)?;
+    let grid = render(
+        &tree,
+        base.as_ref(),
+        args.message.as_deref(),
+        size.0,
+        size.1,
+    );
+    if args.print {
+        let mut out = io::stdout().lock();
+        write!(out, "{}", grid.to_ansi_string(args.color_support()))?;
+        out.flush()?;
+    }
+
+    let mut palette = Palette::default();
+    for (index, hex) in &args.export_color {
+        palette.ansi[*index as usize] = hex.clone();
+    }
+    if let Some(background) = &args.export_background {
+        palette.background = background.clone();
+    }
+
+    if let Some(path) = &args.export_svg {
+        fs::write(path, to_svg(&grid, &palette)).map_err(Error::file(path))?;
+    }
+
+    if let Some(path) = &args.export_gif {
+        let options = GifOptions {
+            width: size.0,
+            height: size.1,
+            base: base.as_ref(),
+            message: args.message.as_deref(),
+            time: args.time,
+            wait: args.wait,
+            scale: 2,
+            palette: &palette,
+        };
+        let file = fs::File::create(path).map_err(Error::file(path))?;
+        write_gif(io::BufWriter::new(file), &tree, &options).map_err(|err| match err {
+            Error::Io(source) => Error::File {
+                path: path.clone(),
+                source,
+            },
+            err => err,
+        })?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,111 @@
+use crate::style::Color;
+
+/// Hex colors used when exporting a tree to an image
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub background: String,
+    /// Color for text using the terminal's default foreground
+    pub foreground: String,
+    /// The 16 standard ANSI colors
+    pub ansi: [String; 16],
+}
+
+impl Default for Palette {
+    /// The xterm default colors on a black background
+    fn default() -> Self {
+        let ansi = [
+            "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+            "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+        ];
+        Palette {
+            background: "#000000".to_string(),
+            foreground: "#e5e5e5".to_string(),
+            ansi: ansi.map(String::from),
+        }
+    }
+}
+
+impl Palette {
+    /// The hex color for a terminal color. Colors from the 256 color cube and
+    /// grayscale ramp are computed, true colors are passed through
+    pub fn hex(&self, color: Color) -> String {
+        let index = match color {
+            Color::Reset => return self.foreground.clone(),
+            Color::Rgb { r, g, b } => return format!("#{:02x}{:02x}{:02x}", r, g, b),
+            Color::AnsiValue(value) => value,
+            Color::Black => 0,
+            Color::DarkRed => 1,
+            Color::DarkGreen => 2,
+            Color::DarkYellow => 3,
+            Color::DarkBlue => 4,
+            Color::DarkMagenta => 5,
+            Color::DarkCyan => 6,
+            Color::Grey => 7,
+            Color::DarkGrey => 8,
+            Color::Red => 9,
+            Color::Green => 10,
+            Color::Yellow => 11,
+            Color::Blue => 12,
+            Color::Magenta => 13,
+            Color::Cyan => 14,
+            Color::White => 15,
+        };
+        match index {
+            0..=15 => self.ansi[index as usize].clone(),
+            16..=231 => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let cube = index - 16;
+                format!(
+                    "#{:02x}{:02x}{:02x}",
+                    level(cube / 36),
+                    level((cube / 6) % 6),
+                    level(cube % 6)
+                )
+            }
+            _ => {
+                let gray = 8 + (index - 232) * 10;
+                format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+            }
+        }
+    }
+
+    /// The red, green and blue components of a terminal color
+    pub fn rgb(&self, color: Color) -> [u8; 3] {
+        hex_to_rgb(&self.hex(color))
+    }
+
+    pub fn background_rgb(&self) -> [u8; 3] {
+        hex_to_rgb(&self.background)
+    }
+}
+
+pub(crate) fn hex_to_rgb(hex: &str) -> [u8; 3] {
+    let digits = hex.trim_start_matches('#');
+    let channel =
+        |i: usize| u8::from_str_radix(digits.get(i..i + 2).unwrap_or("00"), 16).unwrap_or(0);
+    [channel(0), channel(2), channel(4)]
+}
+
+/// Parse an `--export-color` override of the form `INDEX=#RRGGBB`
+pub fn parse_color_override(spec: &str) -> Result<(u8, String), String> {
+    let (index, hex) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected INDEX=#RRGGBB, got '{}'", spec))?;
+    let index: u8 = index
+        .trim()
+        .parse()
+        .ok()
+        .filter(|index| *index < 16)
+        .ok_or_else(|| format!("color index must be between 0 and 15, got '{}'", index))?;
+    Ok((index, parse_hex(hex)?))
+}
+
+/// Check that `hex` is a `#RRGGBB` color
+pub fn parse_hex(hex: &str) -> Result<String, String> {
+    let hex = hex.trim();
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected a color like #RRGGBB, got '{}'", hex));
+    }
+    Ok(format!("#{}", digits.to_ascii_lowercase()))
+}
@@ -0,0 +1,95 @@
+use std::{
+    io::{self, Write},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+/// Writes output events in asciinema's asciicast v2 format
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording of a terminal with the given size
+    pub fn new(mut out: W, width: u16, height: u16) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        writeln!(
+            out,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}}}"#,
+            width, height, timestamp
+        )?;
+        Ok(Recorder {
+            out,
+            start: Instant::now(),
+        })
+    }
+
+    /// Record `data` as written to the terminal now
+    pub fn record(&mut self, data: &[u8]) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        let event = json!([time, "o", String::from_utf8_lossy(data)]);
+        writeln!(self.out, "{}", event)?;
+        self.out.flush()
+    }
+
+    /// Record that the terminal changed to the given size now
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        let event = json!([time, "r", format!("{}x{}", width, height)]);
+        writeln!(self.out, "{}", event)?;
+        self.out.flush()
+    }
+}
+
+/// A writer that passes everything on to `inner` and, if recording, records
+/// it as one event each time it is flushed
+pub struct Tee<W: Write, R: Write> {
+    inner: W,
+    recorder: Option<Recorder<R>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write, R: Write> Tee<W, R> {
+    pub fn new(inner: W, recorder: Option<Recorder<R>>) -> Self {
+        Tee {
+            inner,
+            recorder,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Record a change in the terminal's size, after anything written
+    /// before it
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<()> {
+        self.flush()?;
+        match &mut self.recorder {
+            Some(recorder) => recorder.resize(width, height),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write, R: Write> Write for Tee<W, R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if self.recorder.is_some() {
+            self.buffer.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if let Some(recorder) = &mut self.recorder {
+            if !self.buffer.is_empty() {
+                recorder.record(&self.buffer)?;
+                self.buffer.clear();
+            }
+        }
+        Ok(())
+    }
+}
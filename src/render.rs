@@ -0,0 +1,241 @@
+use std::fmt::Write;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    base::BaseArt,
+    bonsai::{
+        utility::{message_style, message_window, Style},
+        Val,
+    },
+    style::{Attribute, Color},
+    theme::ColorSupport,
+};
+
+/// A single character cell of a rendered tree
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    /// The text shown in this cell. Empty if the cell is covered by a wide
+    /// character in the cell to its left
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: " ".to_string(),
+            style: Style::default(),
+        }
+    }
+}
+
+/// An in-memory screen that trees, bases and messages can be drawn onto
+/// without a terminal
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(width: u16, height: u16) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get(self.index(x, y))
+    }
+
+    /// The cells of row `y`, left to right
+    pub fn row(&self, y: u16) -> &[Cell] {
+        let start = self.index(0, y);
+        &self.cells[start..start + self.width as usize]
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Write `text` starting at column `x` of row `y`. Anything falling
+    /// outside of the grid is clipped
+    pub fn put_str(&mut self, x: i32, y: i32, text: &str, style: &Style) {
+        if y < 0 || y >= self.height as i32 {
+            return;
+        }
+        let mut x = x;
+        // the column of the last character written, so zero width characters
+        // can be attached to it
+        let mut last: Option<i32> = None;
+        let mut join_next = false;
+        for c in text.chars() {
+            let width = c.width().unwrap_or(0) as i32;
+            if width == 0 || join_next {
+                if let Some(col) = last {
+                    let index = self.index(col as u16, y as u16);
+                    self.cells[index].symbol.push(c);
+                }
+                join_next = c == '\u{200d}';
+                continue;
+            }
+            if x >= 0 && x + width <= self.width as i32 {
+                self.set(x as u16, y as u16, c, width as u16, style);
+                last = Some(x);
+            } else {
+                last = None;
+            }
+            x += width;
+        }
+    }
+
+    fn set(&mut self, x: u16, y: u16, c: char, width: u16, style: &Style) {
+        // don't leave half of a wide character behind when overwriting it
+        let index = self.index(x, y);
+        if self.cells[index].symbol.is_empty() && x > 0 {
+            self.cells[index - 1] = Cell::default();
+        }
+        let end = self.index(x + width - 1, y);
+        if x + width < self.width && self.cells[end + 1].symbol.is_empty() {
+            self.cells[end + 1] = Cell::default();
+        }
+
+        self.cells[index] = Cell {
+            symbol: c.to_string(),
+            style: style.clone(),
+        };
+        for covered in index + 1..=end {
+            self.cells[covered] = Cell {
+                symbol: String::new(),
+                style: style.clone(),
+            };
+        }
+    }
+
+    pub fn draw_tree(&mut self, tree: &[Val]) {
+        for val in tree {
+            self.put_str(val.pos.x, val.pos.y, &val.char, &val.style);
+        }
+    }
+
+    /// Draw the base centered at the bottom of the grid
+    pub fn draw_base(&mut self, art: &BaseArt) {
+        let (start_x, start_y) = art.origin(self.width, self.height);
+        for (i, line) in art.lines.iter().enumerate() {
+            let mut x = start_x as i32;
+            for span in line {
+                self.put_str(x, (start_y as usize + i) as i32, &span.text, &span.style);
+                x += span.text.width() as i32;
+            }
+        }
+    }
+
+    pub fn draw_message(&mut self, message: &str) {
+        let style = message_style();
+        for (x, y, text) in message_window(message, self.width, self.height) {
+            self.put_str(x, y, &text, &style);
+        }
+    }
+
+    /// The grid as plain text, one line per row with trailing spaces removed
+    pub fn to_plain_string(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.height {
+            let line: String = self
+                .row(y)
+                .iter()
+                .map(|cell| cell.symbol.as_str())
+                .collect();
+            out.push_str(line.trim_end_matches(' '));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The grid as text with ANSI escape sequences for colors and attributes
+    pub fn to_ansi_string(&self, colors: ColorSupport) -> String {
+        if !colors.enabled() {
+            return self.to_plain_string();
+        }
+        let plain = Style::default();
+        let mut out = String::new();
+        for y in 0..self.height {
+            let row = self.row(y);
+            let end = row
+                .iter()
+                .rposition(|cell| *cell != Cell::default())
+                .map_or(0, |i| i + 1);
+
+            let mut current = &plain;
+            for cell in &row[..end] {
+                if cell.symbol.is_empty() {
+                    continue;
+                }
+                if cell.style != *current {
+                    write_style(&mut out, &cell.style, colors);
+                    current = &cell.style;
+                }
+                out.push_str(&cell.symbol);
+            }
+            if *current != plain {
+                write_sgr(&mut out, &Attribute::Reset.sgr().to_string());
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Write a Select Graphic Rendition escape sequence
+fn write_sgr(out: &mut String, params: &str) {
+    // writing to a String can't fail
+    let _ = write!(out, "\x1b[{}m", params);
+}
+
+fn write_style(out: &mut String, style: &Style, colors: ColorSupport) {
+    write_sgr(out, &Attribute::Reset.sgr().to_string());
+    if style.attribute != Attribute::Reset {
+        write_sgr(out, &style.attribute.sgr().to_string());
+    }
+    if style.foreground_color != Color::Reset {
+        write_sgr(out, &colors.adapt(style.foreground_color).sgr(false));
+    }
+    if style.background_color != Color::Reset {
+        write_sgr(out, &colors.adapt(style.background_color).sgr(true));
+    }
+}
+
+/// Render a tree, its base and an optional message onto a grid of the given
+/// size, in the same layout the terminal would show
+pub fn render(
+    tree: &[Val],
+    base: Option<&BaseArt>,
+    message: Option<&str>,
+    width: u16,
+    height: u16,
+) -> Grid {
+    let mut grid = Grid::new(width, height);
+    if let Some(base) = base {
+        grid.draw_base(base);
+    }
+    grid.draw_tree(tree);
+    if let Some(message) = message {
+        grid.draw_message(message);
+    }
+    grid
+}
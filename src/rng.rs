@@ -0,0 +1,85 @@
+/// Bumped whenever the same seed would grow a different tree, whether the
+/// random numbers or the way they are used changed. Shared seeds are written
+/// `GENERATION:SEED` so an old one is caught instead of growing a different
+/// tree
+pub const GENERATION_VERSION: u32 = 1;
+
+/// The random number generator trees are grown with: SplitMix64 (Steele, Lea
+/// and Flood, 2014), with ranges drawn by rejection sampling. Both are
+/// implemented here rather than taken from a crate so a seed grows the same
+/// tree on every platform and every release with the same
+/// [`GENERATION_VERSION`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeRng {
+    state: u64,
+}
+
+impl TreeRng {
+    pub fn new(seed: u64) -> Self {
+        TreeRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// The high half of the next 64 bits
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// A number from `low` up to but not including `high`, each equally
+    /// likely. Gives `low` if the range is empty
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+        let span = high.abs_diff(low);
+        // the last partial run of `span` values is thrown away so every
+        // value has the same number of ways to be drawn
+        let limit = u32::MAX - (u32::MAX - span + 1) % span;
+        loop {
+            let value = self.next_u32();
+            if value <= limit {
+                return low.wrapping_add((value % span) as i32);
+            }
+        }
+    }
+
+    /// True one time in `n`
+    pub fn one_in(&mut self, n: i32) -> bool {
+        self.range(0, n) == 0
+    }
+}
+
+/// Parse a `--seed` argument, either a plain number or `GENERATION:SEED`.
+/// Seeds from another generation are refused since they would grow a
+/// different tree
+pub fn parse_seed(text: &str) -> Result<u64, String> {
+    let (generation, seed) = match text.split_once(':') {
+        Some((generation, seed)) => (Some(generation), seed),
+        None => (None, text),
+    };
+    if let Some(generation) = generation {
+        let generation: u32 = generation
+            .parse()
+            .map_err(|_| format!("`{}` is not a generation number", generation))?;
+        if generation != GENERATION_VERSION {
+            return Err(format!(
+                "the seed is for generation {} trees, but this version of rbonsai grows generation {} trees",
+                generation, GENERATION_VERSION
+            ));
+        }
+    }
+    seed.parse()
+        .map_err(|_| format!("`{}` is not a number", seed))
+}
+
+/// A seed as it should be shared, with the generation it grows
+pub fn seed_string(seed: u64) -> String {
+    format!("{}:{}", GENERATION_VERSION, seed)
+}
@@ -0,0 +1,118 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[cfg(feature = "cli")]
+use crate::{rng::GENERATION_VERSION, Config};
+use crate::{
+    base::{Base, BaseSpec},
+    bonsai::Val,
+    lsystem::LSystem,
+    Error, Result,
+};
+
+/// A grown tree along with everything needed to replay it
+#[derive(Serialize, Deserialize)]
+pub struct SavedTree {
+    pub seed: u64,
+    /// The generation the seed grew the tree with. Trees saved before
+    /// generations were recorded are 0
+    #[serde(default)]
+    pub generation: u32,
+    pub life: i32,
+    pub multiplier: i32,
+    #[serde(deserialize_with = "deserialize_base")]
+    pub base: String,
+    /// The base when it was read from a file, which is used instead of
+    /// `base`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_file: Option<Base>,
+    #[serde(default = "default_species")]
+    pub species: String,
+    /// The grammar the tree was grown from instead of a species
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lsystem: Option<LSystem>,
+    /// Terminal width the tree was grown against
+    pub width: u16,
+    /// Terminal height the tree was grown against
+    pub height: u16,
+    pub tree: Vec<Val>,
+}
+
+impl SavedTree {
+    #[cfg(feature = "cli")]
+    pub fn new(config: &Config, seed: u64, (width, height): (u16, u16), tree: Vec<Val>) -> Self {
+        SavedTree {
+            seed,
+            generation: GENERATION_VERSION,
+            life: config.life,
+            multiplier: config.multiplier,
+            base: config.base.clone(),
+            base_file: config.base_file.clone(),
+            species: config.species.clone(),
+            lsystem: config.lsystem.clone(),
+            width,
+            height,
+            tree,
+        }
+    }
+
+    /// Set the options the tree was grown with, so it is drawn the way it
+    /// was saved
+    #[cfg(feature = "cli")]
+    pub fn apply_to(&self, config: &mut Config) {
+        config.seed = Some(self.seed);
+        config.life = self.life;
+        config.multiplier = self.multiplier;
+        config.base = self.base.clone();
+        config.base_file = self.base_file.clone();
+        config.species = self.species.clone();
+        config.lsystem = self.lsystem.clone();
+    }
+
+    /// Shift the tree so it stays centered above the base on a terminal of
+    /// the given size
+    pub fn fit_to(&mut self, (width, height): (u16, u16)) {
+        let dx = (width / 2) as i32 - (self.width / 2) as i32;
+        let dy = height as i32 - self.height as i32;
+        for val in &mut self.tree {
+            val.pos.x += dx;
+            val.pos.y += dy;
+        }
+        self.width = width;
+        self.height = height;
+    }
+}
+
+/// Bases used to be saved by number
+fn deserialize_base<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    BaseSpec::deserialize(deserializer)?
+        .name()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Trees saved before there were species
+fn default_species() -> String {
+    "default".to_string()
+}
+
+pub fn save_tree(path: &Path, saved: &SavedTree) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path).map_err(Error::file(path))?);
+    serde_json::to_writer(&mut writer, saved).map_err(|err| Error::file(path)(err.into()))?;
+    writer.flush().map_err(Error::file(path))
+}
+
+pub fn load_tree(path: &Path) -> Result<SavedTree> {
+    let reader = BufReader::new(File::open(path).map_err(Error::file(path))?);
+    serde_json::from_reader(reader).map_err(|err| {
+        if err.is_io() {
+            Error::file(path)(err.into())
+        } else {
+            Error::parse(path, err)
+        }
+    })
+}
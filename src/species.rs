@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{bonsai::utility::TreeColors, rng::TreeRng, style::Color};
+
+/// Names of the built-in species, in the order `--help` lists them
+pub const BUILTIN_SPECIES: [&str; 7] = [
+    "default",
+    "juniper",
+    "weeping-willow",
+    "pine",
+    "maple",
+    "cascade",
+    "broom",
+];
+
+/// Values to choose from at random, each with a relative weight
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Weights(pub Vec<(i32, i32)>);
+
+impl Weights {
+    /// Every value from `low` to `high` with the same weight
+    pub fn uniform(low: i32, high: i32) -> Self {
+        Weights((low..=high).map(|value| (value, 1)).collect())
+    }
+
+    /// Always `value`
+    pub fn fixed(value: i32) -> Self {
+        Weights(vec![(value, 1)])
+    }
+
+    /// Pick a value. With a single value the rng is left untouched
+    pub fn pick(&self, rng: &mut TreeRng) -> i32 {
+        let total: i32 = self.0.iter().map(|(_, weight)| weight).sum();
+        match self.0.as_slice() {
+            [] => 0,
+            [(value, _)] => *value,
+            _ if total <= 0 => self.0[0].0,
+            values => {
+                let mut roll = rng.range(0, total);
+                for (value, weight) in values {
+                    if roll < *weight {
+                        return *value;
+                    }
+                    roll -= weight;
+                }
+                values[values.len() - 1].0
+            }
+        }
+    }
+
+    /// The same weights for the opposite direction
+    pub fn mirrored(&self) -> Self {
+        Weights(
+            self.0
+                .iter()
+                .map(|(value, weight)| (-value, *weight))
+                .collect(),
+        )
+    }
+}
+
+/// How far a branch moves in each step. `dy` is picked before `dx`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deltas {
+    pub dx: Weights,
+    /// Negative is up
+    pub dy: Weights,
+}
+
+/// Everything that gives a kind of tree its shape
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    /// The first few steps of the trunk, and any trunk close to dying
+    pub sapling: Deltas,
+    /// Sideways steps of the trunk while it spreads out. It rises once
+    /// every `multiplier / 2` steps
+    pub spread: Weights,
+    /// How long the trunk spreads out for, in multiples of the multiplier
+    pub spread_steps: i32,
+    /// The trunk once it has spread out
+    pub trunk: Deltas,
+    /// Shoots growing left. Shoots growing right are mirrored
+    pub shoot: Deltas,
+    pub dying: Deltas,
+    pub dead: Deltas,
+    /// One in this many steps of the trunk tries to branch, along with
+    /// every step where its life is a multiple of the multiplier
+    pub branch_chance: i32,
+    /// One in this many branches splits the trunk instead of growing a
+    /// shoot
+    pub split_chance: i32,
+    /// Steps between shoots, in multiples of the multiplier
+    pub shoot_spacing: i32,
+    /// Life shoots get on top of the trunk's life and the multiplier
+    pub shoot_life: i32,
+    /// Leaves to use unless `--leaf` is given
+    pub leaves: Option<Vec<String>>,
+    /// Colors to use unless `--theme` is given
+    pub colors: Option<TreeColors>,
+}
+
+impl Default for Species {
+    /// The shape of the original cbonsai trees
+    fn default() -> Self {
+        Species {
+            sapling: Deltas {
+                dx: Weights::uniform(-1, 1),
+                dy: Weights::fixed(0),
+            },
+            spread: Weights(vec![(-2, 1), (-1, 3), (0, 2), (1, 3), (2, 1)]),
+            spread_steps: 3,
+            trunk: Deltas {
+                dx: Weights::uniform(-1, 1),
+                dy: Weights(vec![(0, 3), (-1, 7)]),
+            },
+            shoot: Deltas {
+                dx: Weights(vec![(-2, 2), (-1, 4), (0, 3), (1, 1)]),
+                dy: Weights(vec![(-1, 2), (0, 6), (1, 2)]),
+            },
+            dying: Deltas {
+                dx: Weights(vec![
+                    (-3, 1),
+                    (-2, 2),
+                    (-1, 3),
+                    (0, 3),
+                    (1, 3),
+                    (2, 2),
+                    (3, 1),
+                ]),
+                dy: Weights(vec![(-1, 2), (0, 7), (1, 1)]),
+            },
+            dead: Deltas {
+                dx: Weights::uniform(-1, 1),
+                dy: Weights(vec![(-1, 3), (0, 4), (1, 3)]),
+            },
+            branch_chance: 3,
+            split_chance: 8,
+            shoot_spacing: 2,
+            shoot_life: 0,
+            leaves: None,
+            colors: None,
+        }
+    }
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+fn leaves(leaves: &[&str]) -> Option<Vec<String>> {
+    Some(leaves.iter().map(|leaf| leaf.to_string()).collect())
+}
+
+impl Species {
+    /// One of the [`BUILTIN_SPECIES`]
+    pub fn builtin(name: &str) -> Option<Species> {
+        let default = Species::default();
+        let species = match name {
+            "default" => default,
+            // a gnarled trunk that twists from side to side, with dense
+            // blue-green foliage
+            "juniper" => Species {
+                spread: Weights(vec![(-3, 1), (-2, 2), (-1, 2), (1, 2), (2, 2), (3, 1)]),
+                spread_steps: 4,
+                trunk: Deltas {
+                    dx: Weights(vec![(-2, 1), (-1, 2), (0, 1), (1, 2), (2, 1)]),
+                    dy: Weights(vec![(0, 4), (-1, 6)]),
+                },
+                dying: Deltas {
+                    dx: Weights::uniform(-2, 2),
+                    dy: Weights(vec![(-1, 3), (0, 6), (1, 1)]),
+                },
+                branch_chance: 2,
+                leaves: leaves(&["&", "%", "#"]),
+                colors: Some(TreeColors {
+                    trunk: rgb(120, 85, 60),
+                    trunk_bright: rgb(170, 130, 95),
+                    shoot: rgb(120, 85, 60),
+                    shoot_bright: rgb(170, 130, 95),
+                    dying: rgb(60, 110, 90),
+                    dead: rgb(95, 150, 125),
+                }),
+                ..default
+            },
+            // shoots arc out and hang down in long strands
+            "weeping-willow" => Species {
+                shoot: Deltas {
+                    dx: Weights(vec![(-2, 3), (-1, 4), (0, 2), (1, 1)]),
+                    dy: Weights(vec![(-1, 2), (0, 3), (1, 5)]),
+                },
+                dying: Deltas {
+                    dx: Weights(vec![(-1, 1), (0, 4), (1, 1)]),
+                    dy: Weights(vec![(0, 2), (1, 8)]),
+                },
+                dead: Deltas {
+                    dx: Weights::uniform(-1, 1),
+                    dy: Weights(vec![(0, 3), (1, 7)]),
+                },
+                shoot_life: 4,
+                leaves: leaves(&["'", ";", ","]),
+                colors: Some(TreeColors {
+                    trunk: rgb(100, 85, 60),
+                    trunk_bright: rgb(140, 120, 85),
+                    shoot: rgb(100, 85, 60),
+                    shoot_bright: rgb(140, 120, 85),
+                    dying: rgb(120, 170, 60),
+                    dead: rgb(175, 210, 95),
+                }),
+                ..default
+            },
+            // a straight trunk with short, level shoots all the way up
+            "pine" => Species {
+                spread: Weights(vec![(-1, 1), (0, 6), (1, 1)]),
+                spread_steps: 1,
+                trunk: Deltas {
+                    dx: Weights(vec![(-1, 1), (0, 8), (1, 1)]),
+                    dy: Weights(vec![(0, 1), (-1, 9)]),
+                },
+                shoot: Deltas {
+                    dx: Weights(vec![(-2, 4), (-1, 5), (0, 1)]),
+                    dy: Weights(vec![(-1, 1), (0, 8), (1, 1)]),
+                },
+                dying: Deltas {
+                    dx: Weights::uniform(-2, 2),
+                    dy: Weights(vec![(-1, 1), (0, 8), (1, 1)]),
+                },
+                branch_chance: 2,
+                split_chance: 40,
+                shoot_spacing: 1,
+                shoot_life: -4,
+                leaves: leaves(&["^", "*"]),
+                colors: Some(TreeColors {
+                    trunk: rgb(95, 60, 40),
+                    trunk_bright: rgb(135, 90, 60),
+                    shoot: rgb(95, 60, 40),
+                    shoot_bright: rgb(135, 90, 60),
+                    dying: rgb(30, 90, 50),
+                    dead: rgb(60, 130, 70),
+                }),
+                ..default
+            },
+            // a broad crown of rising shoots and red leaves
+            "maple" => Species {
+                shoot: Deltas {
+                    dx: Weights(vec![(-2, 3), (-1, 4), (0, 2), (1, 1)]),
+                    dy: Weights(vec![(-1, 3), (0, 5), (1, 2)]),
+                },
+                dying: Deltas {
+                    dx: Weights::uniform(-3, 3),
+                    dy: Weights(vec![(-1, 3), (0, 6), (1, 1)]),
+                },
+                shoot_life: 2,
+                leaves: leaves(&["*", "&"]),
+                colors: Some(TreeColors {
+                    trunk: rgb(90, 60, 45),
+                    trunk_bright: rgb(130, 90, 65),
+                    shoot: rgb(90, 60, 45),
+                    shoot_bright: rgb(130, 90, 65),
+                    dying: rgb(200, 50, 30),
+                    dead: rgb(240, 130, 40),
+                }),
+                ..default
+            },
+            // the trunk leans hard to one side and spills over the edge of
+            // the pot
+            "cascade" => Species {
+                spread: Weights(vec![(0, 1), (1, 4), (2, 3)]),
+                spread_steps: 3,
+                trunk: Deltas {
+                    dx: Weights(vec![(0, 2), (1, 5), (2, 2)]),
+                    dy: Weights(vec![(-1, 3), (0, 5), (1, 2)]),
+                },
+                shoot: Deltas {
+                    dx: Weights(vec![(-1, 3), (0, 3), (1, 4)]),
+                    dy: Weights(vec![(-1, 2), (0, 4), (1, 4)]),
+                },
+                dying: Deltas {
+                    dx: Weights(vec![(-1, 2), (0, 3), (1, 3), (2, 2)]),
+                    dy: Weights(vec![(-1, 2), (0, 5), (1, 3)]),
+                },
+                split_chance: 20,
+                ..default
+            },
+            // a straight trunk that splits into a round fan of branches
+            "broom" => Species {
+                spread: Weights::fixed(0),
+                spread_steps: 2,
+                trunk: Deltas {
+                    dx: Weights(vec![(-1, 1), (0, 2), (1, 1)]),
+                    dy: Weights(vec![(0, 4), (-1, 6)]),
+                },
+                shoot: Deltas {
+                    dx: Weights(vec![(-2, 2), (-1, 5), (0, 3)]),
+                    dy: Weights(vec![(-1, 4), (0, 6)]),
+                },
+                branch_chance: 2,
+                split_chance: 3,
+                shoot_spacing: 1,
+                ..default
+            },
+            _ => return None,
+        };
+        Some(species)
+    }
+}
+
+/// Parse a `--species` argument into the name of a built-in species
+pub fn parse_species_name(name: &str) -> Result<String, String> {
+    match Species::builtin(name) {
+        Some(_) => Ok(name.to_string()),
+        None => Err(format!(
+            "unknown species `{}`, the built-in species are {}",
+            name,
+            BUILTIN_SPECIES.join(", ")
+        )),
+    }
+}
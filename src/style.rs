@@ -0,0 +1,268 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A color a terminal can show. Kept separate from any terminal library so
+/// trees can be grown and exported without one
+///
+/// The names are those of the 16 standard colors in crossterm, and colors are
+/// saved the same way crossterm saves them, with `"reset"` for the terminal's
+/// default color
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The terminal's default color
+    Reset,
+    Black,
+    DarkGrey,
+    Red,
+    DarkRed,
+    Green,
+    DarkGreen,
+    Yellow,
+    DarkYellow,
+    Blue,
+    DarkBlue,
+    Magenta,
+    DarkMagenta,
+    Cyan,
+    DarkCyan,
+    White,
+    Grey,
+    Rgb { r: u8, g: u8, b: u8 },
+    /// A color from the 256 color palette
+    AnsiValue(u8),
+}
+
+/// The names of the 16 standard colors, in palette order
+const NAMED: [(Color, &str); 16] = [
+    (Color::Black, "black"),
+    (Color::DarkRed, "dark_red"),
+    (Color::DarkGreen, "dark_green"),
+    (Color::DarkYellow, "dark_yellow"),
+    (Color::DarkBlue, "dark_blue"),
+    (Color::DarkMagenta, "dark_magenta"),
+    (Color::DarkCyan, "dark_cyan"),
+    (Color::Grey, "grey"),
+    (Color::DarkGrey, "dark_grey"),
+    (Color::Red, "red"),
+    (Color::Green, "green"),
+    (Color::Yellow, "yellow"),
+    (Color::Blue, "blue"),
+    (Color::Magenta, "magenta"),
+    (Color::Cyan, "cyan"),
+    (Color::White, "white"),
+];
+
+impl Color {
+    /// The index of one of the 16 standard colors in the 256 color palette
+    pub fn standard_index(self) -> Option<u8> {
+        NAMED
+            .iter()
+            .position(|(color, _)| *color == self)
+            .map(|index| index as u8)
+    }
+
+    /// The standard color at `index` of the palette, or the palette color
+    /// for anything past the first 16
+    pub fn from_index(index: u8) -> Color {
+        match NAMED.get(index as usize) {
+            Some((color, _)) => *color,
+            None => Color::AnsiValue(index),
+        }
+    }
+
+    /// The parameters of an SGR escape sequence that sets this color
+    pub(crate) fn sgr(self, background: bool) -> String {
+        let (set, reset) = if background { (48, 49) } else { (38, 39) };
+        match self {
+            Color::Reset => reset.to_string(),
+            Color::Rgb { r, g, b } => format!("{};2;{};{};{}", set, r, g, b),
+            Color::AnsiValue(index) => format!("{};5;{}", set, index),
+            color => format!("{};5;{}", set, color.standard_index().unwrap_or(0)),
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Color::Reset => "reset".to_string(),
+            Color::Rgb { r, g, b } => format!("rgb_({},{},{})", r, g, b),
+            Color::AnsiValue(index) => format!("ansi_({})", index),
+            color => NAMED[color.standard_index().unwrap_or(0) as usize]
+                .1
+                .to_string(),
+        }
+    }
+
+    fn parse(text: &str) -> Option<Color> {
+        let lower = text.to_lowercase();
+        if lower == "reset" {
+            return Some(Color::Reset);
+        }
+        if let Some((color, _)) = NAMED.iter().find(|(_, name)| *name == lower) {
+            return Some(*color);
+        }
+        if let Some(index) = lower
+            .strip_prefix("ansi_(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return index.parse().ok().map(Color::AnsiValue);
+        }
+        if let Some(rgb) = lower
+            .strip_prefix("rgb_(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let rgb: Vec<u8> = rgb
+                .split(',')
+                .map(|value| value.trim().parse().ok())
+                .collect::<Option<_>>()?;
+            let [r, g, b] = rgb[..] else {
+                return None;
+            };
+            return Some(Color::Rgb { r, g, b });
+        }
+        let hex = crate::palette::parse_hex(text).ok()?;
+        let [r, g, b] = crate::palette::hex_to_rgb(&hex);
+        Some(Color::Rgb { r, g, b })
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        struct ColorVisitor;
+
+        impl de::Visitor<'_> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a color name, `reset`, `ansi_(value)`, `rgb_(r,g,b)` or `#rrggbb`")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Color, E> {
+                Color::parse(text).ok_or_else(|| E::invalid_value(de::Unexpected::Str(text), &self))
+            }
+        }
+
+        deserializer.deserialize_str(ColorVisitor)
+    }
+}
+
+/// How text is drawn, apart from its colors. Saved by name, the same way
+/// crossterm saves them
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Attribute {
+    /// No attribute
+    Reset,
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    SlowBlink,
+    RapidBlink,
+    Reverse,
+    Hidden,
+    CrossedOut,
+    /// Neither bold nor dim
+    NormalIntensity,
+}
+
+impl Attribute {
+    /// The parameter of an SGR escape sequence that turns this on
+    pub(crate) fn sgr(self) -> u8 {
+        match self {
+            Attribute::Reset => 0,
+            Attribute::Bold => 1,
+            Attribute::Dim => 2,
+            Attribute::Italic => 3,
+            Attribute::Underlined => 4,
+            Attribute::SlowBlink => 5,
+            Attribute::RapidBlink => 6,
+            Attribute::Reverse => 7,
+            Attribute::Hidden => 8,
+            Attribute::CrossedOut => 9,
+            Attribute::NormalIntensity => 22,
+        }
+    }
+}
+
+#[cfg(feature = "crossterm")]
+mod crossterm_conversions {
+    use crossterm::style;
+
+    use super::{Attribute, Color};
+
+    impl From<Color> for style::Color {
+        fn from(color: Color) -> Self {
+            match color {
+                Color::Reset => style::Color::Reset,
+                Color::Black => style::Color::Black,
+                Color::DarkGrey => style::Color::DarkGrey,
+                Color::Red => style::Color::Red,
+                Color::DarkRed => style::Color::DarkRed,
+                Color::Green => style::Color::Green,
+                Color::DarkGreen => style::Color::DarkGreen,
+                Color::Yellow => style::Color::Yellow,
+                Color::DarkYellow => style::Color::DarkYellow,
+                Color::Blue => style::Color::Blue,
+                Color::DarkBlue => style::Color::DarkBlue,
+                Color::Magenta => style::Color::Magenta,
+                Color::DarkMagenta => style::Color::DarkMagenta,
+                Color::Cyan => style::Color::Cyan,
+                Color::DarkCyan => style::Color::DarkCyan,
+                Color::White => style::Color::White,
+                Color::Grey => style::Color::Grey,
+                Color::Rgb { r, g, b } => style::Color::Rgb { r, g, b },
+                Color::AnsiValue(index) => style::Color::AnsiValue(index),
+            }
+        }
+    }
+
+    impl From<style::Color> for Color {
+        fn from(color: style::Color) -> Self {
+            match color {
+                style::Color::Reset => Color::Reset,
+                style::Color::Black => Color::Black,
+                style::Color::DarkGrey => Color::DarkGrey,
+                style::Color::Red => Color::Red,
+                style::Color::DarkRed => Color::DarkRed,
+                style::Color::Green => Color::Green,
+                style::Color::DarkGreen => Color::DarkGreen,
+                style::Color::Yellow => Color::Yellow,
+                style::Color::DarkYellow => Color::DarkYellow,
+                style::Color::Blue => Color::Blue,
+                style::Color::DarkBlue => Color::DarkBlue,
+                style::Color::Magenta => Color::Magenta,
+                style::Color::DarkMagenta => Color::DarkMagenta,
+                style::Color::Cyan => Color::Cyan,
+                style::Color::DarkCyan => Color::DarkCyan,
+                style::Color::White => Color::White,
+                style::Color::Grey => Color::Grey,
+                style::Color::Rgb { r, g, b } => Color::Rgb { r, g, b },
+                style::Color::AnsiValue(index) => Color::AnsiValue(index),
+            }
+        }
+    }
+
+    impl From<Attribute> for style::Attribute {
+        fn from(attribute: Attribute) -> Self {
+            match attribute {
+                Attribute::Reset => style::Attribute::Reset,
+                Attribute::Bold => style::Attribute::Bold,
+                Attribute::Dim => style::Attribute::Dim,
+                Attribute::Italic => style::Attribute::Italic,
+                Attribute::Underlined => style::Attribute::Underlined,
+                Attribute::SlowBlink => style::Attribute::SlowBlink,
+                Attribute::RapidBlink => style::Attribute::RapidBlink,
+                Attribute::Reverse => style::Attribute::Reverse,
+                Attribute::Hidden => style::Attribute::Hidden,
+                Attribute::CrossedOut => style::Attribute::CrossedOut,
+                Attribute::NormalIntensity => style::Attribute::NormalIntensity,
+            }
+        }
+    }
+}
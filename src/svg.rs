@@ -0,0 +1,75 @@
+use std::fmt::Write;
+
+use crate::{palette::Palette, render::Grid, style::Attribute};
+
+const FONT_SIZE: f32 = 16.0;
+const CELL_WIDTH: f32 = FONT_SIZE * 0.6;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.2;
+
+/// Render a grid as an SVG document with one monospace text element per run
+/// of equally styled characters
+pub fn to_svg(grid: &Grid, palette: &Palette) -> String {
+    let width = grid.width() as f32 * CELL_WIDTH;
+    let height = grid.height() as f32 * LINE_HEIGHT;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.1}" height="{h:.1}" viewBox="0 0 {w:.1} {h:.1}">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        palette.background
+    );
+    let _ = writeln!(
+        svg,
+        r#"<g font-family="monospace" font-size="{}" xml:space="preserve">"#,
+        FONT_SIZE
+    );
+
+    for y in 0..grid.height() {
+        let row = grid.row(y);
+        let mut x = 0;
+        while x < row.len() {
+            let style = &row[x].style;
+            let start = x;
+            let mut text = String::new();
+            while x < row.len() && row[x].style == *style {
+                text.push_str(&row[x].symbol);
+                x += 1;
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let baseline = y as f32 * LINE_HEIGHT + FONT_SIZE;
+            let weight = if style.attribute == Attribute::Bold {
+                r#" font-weight="bold""#
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" fill="{}" textLength="{:.1}"{}>{}</text>"#,
+                start as f32 * CELL_WIDTH,
+                baseline,
+                palette.hex(style.foreground_color),
+                (x - start) as f32 * CELL_WIDTH,
+                weight,
+                escape(&text)
+            );
+        }
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,106 @@
+use std::{
+    io::{self, Write},
+    panic,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use crossterm::{
+    cursor, execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{Error, Result};
+
+// Global so the panic hook and signal handler can restore the terminal
+// without access to the guard
+static RAW_MODE: AtomicBool = AtomicBool::new(false);
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+/// The signal the process was asked to stop by, or 0
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Puts the terminal into raw mode on the alternate screen with the cursor
+/// hidden, and puts it back when dropped
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    pub fn new(out: &mut impl Write) -> Result<Self> {
+        // created first so a failure part way through is still undone
+        let guard = TerminalGuard { _private: () };
+        enable_raw_mode().map_err(Error::Terminal)?;
+        RAW_MODE.store(true, Ordering::SeqCst);
+        execute!(out, cursor::Hide)?;
+        ALTERNATE_SCREEN.store(true, Ordering::SeqCst);
+        execute!(out, EnterAlternateScreen)?;
+        Ok(guard)
+    }
+
+    /// Go back to the main screen early, staying in raw mode
+    pub fn leave_alternate_screen(&self, out: &mut impl Write) -> Result<()> {
+        if ALTERNATE_SCREEN.swap(false, Ordering::SeqCst) {
+            execute!(out, LeaveAlternateScreen)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Undo whatever [`TerminalGuard`] changed. Does nothing if the terminal is
+/// already restored, so it is safe to call from anywhere
+pub fn restore() {
+    let mut stdout = io::stdout();
+    if ALTERNATE_SCREEN.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout, LeaveAlternateScreen);
+    }
+    if RAW_MODE.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout, cursor::Show);
+    }
+}
+
+/// Restore the terminal before the default panic message is printed, so it
+/// is readable and the shell is left usable
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        previous(info);
+    }));
+}
+
+/// Fail with [`Error::Signal`] once the process has been asked to stop
+pub fn check_signal() -> Result<()> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => Ok(()),
+        signal => Err(Error::Signal(signal)),
+    }
+}
+
+/// Ask the main loop to stop when the process is asked to, through
+/// [`check_signal`], so the terminal is restored and recordings are finished
+/// on the way out. A second signal restores the terminal and exits straight
+/// away, in case the main loop is stuck
+#[cfg(unix)]
+pub fn handle_signals() -> Result<()> {
+    use signal_hook::{
+        consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM},
+        iterator::Signals,
+    };
+
+    let mut signals = Signals::new([SIGHUP, SIGINT, SIGQUIT, SIGTERM]).map_err(Error::Terminal)?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if SIGNAL.swap(signal, Ordering::SeqCst) != 0 {
+                restore();
+                std::process::exit(128 + signal);
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use std::{env, fs, path::Path};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{bonsai::utility::TreeColors, config_file, style::Color, Error, Result};
+
+/// Names of the themes built into rbonsai
+pub const BUILTIN_THEMES: [&str; 5] = [
+    "default",
+    "autumn",
+    "cherry-blossom",
+    "winter",
+    "monochrome",
+];
+
+/// The colors of each part of the base art
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BaseColors {
+    /// The rim and sides of the pot
+    pub pot: Color,
+    /// The soil along the top of the pot
+    pub soil: Color,
+    /// The bottom of the trunk, planted in the soil
+    pub trunk: Color,
+}
+
+impl Default for BaseColors {
+    fn default() -> Self {
+        BaseColors {
+            pot: Color::AnsiValue(8),
+            soil: Color::AnsiValue(2),
+            trunk: Color::AnsiValue(11),
+        }
+    }
+}
+
+/// Colors for both the tree and its base
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub tree: TreeColors,
+    pub base: BaseColors,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Theme {
+    /// One of the [`BUILTIN_THEMES`]
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            "default" => Theme::default(),
+            "autumn" => Theme {
+                tree: TreeColors {
+                    trunk: rgb(110, 60, 25),
+                    trunk_bright: rgb(150, 85, 40),
+                    shoot: rgb(110, 60, 25),
+                    shoot_bright: rgb(150, 85, 40),
+                    dying: rgb(230, 120, 20),
+                    dead: rgb(190, 40, 25),
+                },
+                base: BaseColors {
+                    pot: rgb(140, 90, 60),
+                    soil: rgb(95, 70, 40),
+                    trunk: rgb(150, 85, 40),
+                },
+            },
+            "cherry-blossom" => Theme {
+                tree: TreeColors {
+                    trunk: rgb(80, 50, 45),
+                    trunk_bright: rgb(115, 75, 65),
+                    shoot: rgb(80, 50, 45),
+                    shoot_bright: rgb(115, 75, 65),
+                    dying: rgb(255, 145, 175),
+                    dead: rgb(255, 200, 215),
+                },
+                base: BaseColors {
+                    pot: rgb(60, 90, 130),
+                    soil: rgb(85, 107, 47),
+                    trunk: rgb(115, 75, 65),
+                },
+            },
+            "winter" => Theme {
+                tree: TreeColors {
+                    trunk: rgb(90, 80, 75),
+                    trunk_bright: rgb(140, 130, 125),
+                    shoot: rgb(90, 80, 75),
+                    shoot_bright: rgb(140, 130, 125),
+                    dying: rgb(170, 210, 235),
+                    dead: rgb(240, 248, 255),
+                },
+                base: BaseColors {
+                    pot: rgb(120, 130, 140),
+                    soil: rgb(225, 235, 245),
+                    trunk: rgb(140, 130, 125),
+                },
+            },
+            "monochrome" => Theme {
+                tree: TreeColors {
+                    trunk: Color::Reset,
+                    trunk_bright: Color::Reset,
+                    shoot: Color::Reset,
+                    shoot_bright: Color::Reset,
+                    dying: Color::Reset,
+                    dead: Color::Reset,
+                },
+                base: BaseColors {
+                    pot: Color::Reset,
+                    soil: Color::Reset,
+                    trunk: Color::Reset,
+                },
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// A built-in theme, a theme file, or a theme named `<name>.toml` in the
+    /// `themes` directory next to the config file
+    pub fn load(name: &str) -> Result<Theme> {
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+        if is_file_name(name) {
+            return Theme::read(Path::new(name));
+        }
+        let file = config_file::config_dir()
+            .map(|dir| dir.join("themes").join(format!("{}.toml", name)))
+            .filter(|file| file.is_file());
+        match file {
+            Some(file) => Theme::read(&file),
+            None => Err(Error::InvalidConfig(format!(
+                "unknown theme `{}`, the built-in themes are {}",
+                name,
+                BUILTIN_THEMES.join(", ")
+            ))),
+        }
+    }
+
+    /// Read a theme file
+    pub fn read(path: &Path) -> Result<Theme> {
+        let text = fs::read_to_string(path).map_err(Error::file(path))?;
+        Theme::parse(path, &text)
+    }
+
+    /// Parse the text of a theme file. `path` is only used in errors
+    pub fn parse(path: &Path, text: &str) -> Result<Theme> {
+        let file: ThemeFile = toml::from_str(text).map_err(|err| Error::parse(path, err))?;
+        let extends = file.extends.as_deref().unwrap_or("default");
+        let mut theme = Theme::builtin(extends).ok_or_else(|| {
+            Error::parse(
+                path,
+                format!("can only extend a built-in theme, not `{}`", extends),
+            )
+        })?;
+
+        let color = |spec: Option<ColorSpec>, default: Color| match spec {
+            Some(spec) => spec.color().map_err(|message| Error::parse(path, message)),
+            None => Ok(default),
+        };
+        let tree = file.tree.unwrap_or_default();
+        theme.tree = TreeColors {
+            trunk: color(tree.trunk, theme.tree.trunk)?,
+            trunk_bright: color(tree.trunk_bright, theme.tree.trunk_bright)?,
+            shoot: color(tree.shoot, theme.tree.shoot)?,
+            shoot_bright: color(tree.shoot_bright, theme.tree.shoot_bright)?,
+            dying: color(tree.dying, theme.tree.dying)?,
+            dead: color(tree.dead, theme.tree.dead)?,
+        };
+        let base = file.base.unwrap_or_default();
+        theme.base = BaseColors {
+            pot: color(base.pot, theme.base.pot)?,
+            soil: color(base.soil, theme.base.soil)?,
+            trunk: color(base.trunk, theme.base.trunk)?,
+        };
+        Ok(theme)
+    }
+}
+
+/// Parse a `--theme` argument
+pub fn parse_theme(name: &str) -> Result<Theme, String> {
+    Theme::load(name).map_err(|err| match err {
+        Error::InvalidConfig(message) => message,
+        err => err.to_string(),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    extends: Option<String>,
+    tree: Option<TreeSection>,
+    base: Option<BaseSection>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct TreeSection {
+    trunk: Option<ColorSpec>,
+    trunk_bright: Option<ColorSpec>,
+    shoot: Option<ColorSpec>,
+    shoot_bright: Option<ColorSpec>,
+    dying: Option<ColorSpec>,
+    dead: Option<ColorSpec>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaseSection {
+    pot: Option<ColorSpec>,
+    soil: Option<ColorSpec>,
+    trunk: Option<ColorSpec>,
+}
+
+/// A color in a theme file: an ANSI color index, `"#rrggbb"`, or
+/// `"default"` for the terminal's own foreground color
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ColorSpec {
+    Index(u8),
+    Text(String),
+}
+
+impl ColorSpec {
+    pub(crate) fn color(self) -> Result<Color, String> {
+        match self {
+            ColorSpec::Index(index) => Ok(Color::AnsiValue(index)),
+            ColorSpec::Text(text) if text == "default" => Ok(Color::Reset),
+            ColorSpec::Text(text) => {
+                let hex = crate::palette::parse_hex(&text)?;
+                let [r, g, b] = crate::palette::hex_to_rgb(&hex);
+                Ok(Color::Rgb { r, g, b })
+            }
+        }
+    }
+}
+
+/// When to use color, from `--color`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// The colors to use when writing to a terminal or not
+    pub fn resolve(self, is_terminal: bool) -> ColorSupport {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        match self {
+            ColorChoice::Always => ColorSupport::detect(),
+            ColorChoice::Auto if is_terminal && !no_color => ColorSupport::detect(),
+            ColorChoice::Auto | ColorChoice::Never => ColorSupport::NoColor,
+        }
+    }
+}
+
+/// How many colors the terminal can show
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// No colors or attributes at all
+    NoColor,
+    /// The 256 color palette
+    Ansi256,
+    /// Any 24-bit color
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Guess from `COLORTERM`, which terminals with 24-bit color set to
+    /// `truecolor` or `24bit`
+    pub fn detect() -> Self {
+        match env::var("COLORTERM") {
+            Ok(value) if value == "truecolor" || value == "24bit" => ColorSupport::TrueColor,
+            _ => ColorSupport::Ansi256,
+        }
+    }
+
+    /// The color to send to the terminal for `color`, replacing 24-bit
+    /// colors with the closest palette color if they aren't supported
+    pub fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::NoColor, _) => Color::Reset,
+            (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => {
+                Color::AnsiValue(closest_ansi256(r, g, b))
+            }
+            _ => color,
+        }
+    }
+
+    /// Whether colors and attributes should be written at all
+    pub fn enabled(self) -> bool {
+        self != ColorSupport::NoColor
+    }
+}
+
+/// The closest color in the 6x6x6 cube or grayscale ramp of the 256 color
+/// palette. The first 16 colors are skipped since terminals theme them
+fn closest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let distance = |[r2, g2, b2]: [u8; 3]| {
+        (r as i32 - r2 as i32).pow(2)
+            + (g as i32 - g2 as i32).pow(2)
+            + (b as i32 - b2 as i32).pow(2)
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (16 + 36 * ri + 6 * gi + bi) as u8;
+    let cube_distance = distance([LEVELS[ri], LEVELS[gi], LEVELS[bi]]);
+
+    let average = (r as i32 + g as i32 + b as i32) / 3;
+    let gray_step = ((average - 8 + 5) / 10).clamp(0, 23);
+    let gray_level = (8 + gray_step * 10) as u8;
+    let gray_distance = distance([gray_level, gray_level, gray_level]);
+
+    if gray_distance < cube_distance {
+        232 + gray_step as u8
+    } else {
+        cube
+    }
+}
+
+/// Whether a theme name is the path of a theme file rather than the name of
+/// a built-in theme or one in the `themes` directory
+pub(crate) fn is_file_name(name: &str) -> bool {
+    let path = Path::new(name);
+    path.extension().is_some() || path.components().count() > 1
+}
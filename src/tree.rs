@@ -0,0 +1,284 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    base::{base_name, parse_base_name, Base, BaseArt},
+    bonsai::{
+        grow_with,
+        growth::{ClassicGrowth, GrowthStrategy},
+        utility::TreeColors,
+        BranchType, Canvas, GrowthParams, TreeStats, Val,
+    },
+    lsystem::LSystem,
+    rng::{TreeRng, GENERATION_VERSION},
+    species::Species,
+    style::Color,
+    theme::{BaseColors, Theme},
+    Error, Result,
+};
+
+/// The smallest rectangle containing every cell of a tree, inclusive
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl Bounds {
+    pub fn width(&self) -> i32 {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max_y - self.min_y + 1
+    }
+}
+
+/// A grown tree along with how it was grown
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tree {
+    /// Every step of growth, in the order it should be drawn
+    pub cells: Vec<Val>,
+    /// The area covered by the tree, or `None` if nothing fit on the canvas
+    pub bounds: Option<Bounds>,
+    pub seed: u64,
+    /// The [`GENERATION_VERSION`] the tree was grown with
+    pub generation: u32,
+    pub params: GrowthParams,
+    pub canvas: Canvas,
+    pub stats: TreeStats,
+}
+
+impl Tree {
+    /// The number of cells of the given branch type
+    pub fn count(&self, branch_type: BranchType) -> usize {
+        self.cells
+            .iter()
+            .filter(|val| val.branch_type == branch_type)
+            .count()
+    }
+}
+
+/// Builds a [`Tree`] without going through the command line [`Config`]
+///
+/// [`Config`]: crate::Config
+#[derive(Clone, Debug)]
+pub struct TreeBuilder {
+    params: GrowthParams,
+    seed: Option<u64>,
+    width: u16,
+    height: u16,
+    base: Option<Base>,
+    unknown_base: Option<String>,
+    lsystem: Option<LSystem>,
+}
+
+impl Default for TreeBuilder {
+    fn default() -> Self {
+        TreeBuilder {
+            params: GrowthParams::default(),
+            seed: None,
+            width: 80,
+            height: 24,
+            base: Base::named("large"),
+            unknown_base: None,
+            lsystem: None,
+        }
+    }
+}
+
+impl TreeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The starting life of the tree; higher -> bigger tree
+    pub fn life(mut self, life: i32) -> Self {
+        self.params.life = life;
+        self
+    }
+
+    /// The branch multiplier; higher -> less branches
+    pub fn multiplier(mut self, multiplier: i32) -> Self {
+        self.params.multiplier = multiplier;
+        self
+    }
+
+    /// Strings randomly chosen for leaves
+    pub fn leaves<I, S>(mut self, leaves: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params.leaves = leaves.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn colors(mut self, colors: TreeColors) -> Self {
+        self.params.colors = colors;
+        self
+    }
+
+    /// The shape of the tree. Also uses the leaves and colors of the
+    /// species if it has them, so set those afterwards to change them
+    pub fn species(mut self, species: Species) -> Self {
+        if let Some(leaves) = &species.leaves {
+            self.params.leaves = leaves.clone();
+        }
+        if let Some(colors) = &species.colors {
+            self.params.colors = colors.clone();
+        }
+        self.params.species = species;
+        self
+    }
+
+    /// Color of the trunk and shoots, and their bold variant
+    pub fn wood_colors(mut self, wood: Color, wood_bright: Color) -> Self {
+        self.params.colors.trunk = wood;
+        self.params.colors.trunk_bright = wood_bright;
+        self.params.colors.shoot = wood;
+        self.params.colors.shoot_bright = wood_bright;
+        self
+    }
+
+    /// Use the tree colors of a theme
+    pub fn theme(mut self, theme: &Theme) -> Self {
+        self.params.colors = theme.tree.clone();
+        self
+    }
+
+    /// Color of the leaves on dying and dead branches
+    pub fn leaf_colors(mut self, dying: Color, dead: Color) -> Self {
+        self.params.colors.dying = dying;
+        self.params.colors.dead = dead;
+        self
+    }
+
+    /// Size of the canvas the tree grows in, including the base
+    pub fn canvas_size(mut self, width: u16, height: u16) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// The built-in base the tree is planted in, by name or number, which
+    /// takes up the bottom of the canvas
+    pub fn base(mut self, name: &str) -> Self {
+        self.base = Base::named(name);
+        self.unknown_base = match base_name(name) {
+            Some(_) => None,
+            None => Some(name.to_string()),
+        };
+        self
+    }
+
+    /// Plant the tree in a base loaded from a file or made by hand
+    pub fn custom_base(mut self, base: Base) -> Self {
+        self.base = Some(base);
+        self.unknown_base = None;
+        self
+    }
+
+    /// Draw the tree from an L-system grammar instead of growing it
+    pub fn lsystem(mut self, lsystem: LSystem) -> Self {
+        self.lsystem = Some(lsystem);
+        self
+    }
+
+    /// Random number seed. Without one, the current time is used
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Pick the seed from the current time now if none was set, so every
+    /// build grows the same tree
+    pub fn pin_seed(mut self) -> Self {
+        self.seed = Some(self.seed.unwrap_or_else(time_seed));
+        self
+    }
+
+    pub fn params(mut self, params: GrowthParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Grow on a canvas of the given size, with the life scaled by how much
+    /// smaller or bigger it is than the canvas size set so far, so the tree
+    /// fills about as much of it
+    pub fn scaled_to(mut self, width: u16, height: u16) -> Self {
+        let scale = (width as f64 / self.width.max(1) as f64)
+            .min(height as f64 / self.height.max(1) as f64);
+        self.params.life = ((self.params.life as f64 * scale).round() as i32).max(1);
+        self.canvas_size(width, height)
+    }
+
+    /// The art of the base the tree is planted in
+    pub fn base_art(&self, colors: &BaseColors) -> Option<BaseArt> {
+        self.base.as_ref().map(|base| base.art(colors))
+    }
+
+    /// Grow the tree. Fails if the growth parameters are out of range or the
+    /// base doesn't exist
+    pub fn build(self) -> Result<Tree> {
+        self.build_with(&mut ClassicGrowth::default())
+    }
+
+    /// Grow the tree by the rules of a custom [`GrowthStrategy`]. An
+    /// L-system is drawn the same way whatever the strategy
+    pub fn build_with(self, strategy: &mut impl GrowthStrategy) -> Result<Tree> {
+        self.params.validate()?;
+        if let Some(name) = self.unknown_base {
+            return Err(Error::InvalidConfig(parse_base_name(&name).unwrap_err()));
+        }
+        let seed = self.seed.unwrap_or_else(time_seed);
+        let canvas = Canvas::new(self.width, self.height, self.base.as_ref());
+        let mut rng = TreeRng::new(seed);
+        let (cells, stats) = match &self.lsystem {
+            Some(lsystem) => (
+                lsystem.grow(&self.params, &mut rng, canvas),
+                TreeStats::default(),
+            ),
+            None => grow_with(strategy, &self.params, &mut rng, canvas),
+        };
+
+        Ok(Tree {
+            bounds: bounds(&cells),
+            cells,
+            seed,
+            generation: GENERATION_VERSION,
+            params: self.params,
+            canvas,
+            stats,
+        })
+    }
+}
+
+fn bounds(cells: &[Val]) -> Option<Bounds> {
+    let first = cells.first()?;
+    let mut bounds = Bounds {
+        min_x: first.pos.x,
+        min_y: first.pos.y,
+        max_x: first.pos.x,
+        max_y: first.pos.y,
+    };
+    for val in cells {
+        let width = val.char.width().max(1) as i32;
+        bounds.min_x = bounds.min_x.min(val.pos.x);
+        bounds.min_y = bounds.min_y.min(val.pos.y);
+        bounds.max_x = bounds.max_x.max(val.pos.x + width - 1);
+        bounds.max_y = bounds.max_y.max(val.pos.y);
+    }
+    Some(bounds)
+}
+
+/// A seed from the current time, for trees that should differ between runs
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
@@ -0,0 +1,240 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{self, Modifier},
+    widgets::{StatefulWidget, Widget},
+};
+
+use crate::{
+    bonsai::{utility::Style, Val},
+    render::{render, Cell, Grid},
+    style::{Attribute, Color},
+    theme::{BaseColors, Theme},
+    tree::TreeBuilder,
+};
+
+/// A tree grown to fit the area it is rendered in, planted in its base, with
+/// an optional message
+///
+/// Rendered as a [`Widget`], the finished tree is shown. Rendered as a
+/// [`StatefulWidget`], it grows one step each time [`BonsaiState::tick`] is
+/// called. Cells the tree doesn't cover are left as they are
+#[derive(Clone, Debug)]
+pub struct BonsaiWidget {
+    builder: TreeBuilder,
+    base_colors: BaseColors,
+    message: Option<String>,
+    scale_life: bool,
+}
+
+impl BonsaiWidget {
+    /// Grow trees the way `builder` is set up to. Without a seed on it, one
+    /// is picked from the current time now, so every render of the widget
+    /// draws the same tree
+    pub fn new(builder: TreeBuilder) -> Self {
+        BonsaiWidget {
+            builder: builder.pin_seed(),
+            base_colors: BaseColors::default(),
+            message: None,
+            scale_life: true,
+        }
+    }
+
+    /// Use the colors of a theme for both the tree and the base
+    pub fn theme(mut self, theme: &Theme) -> Self {
+        self.builder = self.builder.theme(theme);
+        self.base_colors = theme.base.clone();
+        self
+    }
+
+    pub fn base_colors(mut self, colors: BaseColors) -> Self {
+        self.base_colors = colors;
+        self
+    }
+
+    /// Text shown in a box next to the tree once it has finished growing
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Whether the life of the tree is scaled by how much smaller or bigger
+    /// the area is than the canvas size of the builder, 80 by 24 unless it
+    /// was changed. On by default
+    pub fn scale_life(mut self, scale_life: bool) -> Self {
+        self.scale_life = scale_life;
+        self
+    }
+
+    /// The tree for an area of the given size. Empty if the builder can't
+    /// grow one
+    fn grow(&self, width: u16, height: u16) -> Vec<Val> {
+        let builder = if self.scale_life {
+            self.builder.clone().scaled_to(width, height)
+        } else {
+            self.builder.clone().canvas_size(width, height)
+        };
+        builder
+            .build()
+            .map_or_else(|_| Vec::new(), |tree| tree.cells)
+    }
+
+    /// Draw into an area that is inside the buffer
+    fn draw(&self, tree: &[Val], done: bool, area: Rect, buf: &mut Buffer) {
+        let grid = render(
+            tree,
+            self.builder.base_art(&self.base_colors).as_ref(),
+            self.message.as_deref().filter(|_| done),
+            area.width,
+            area.height,
+        );
+        copy(&grid, area, buf);
+    }
+}
+
+impl Widget for BonsaiWidget {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Widget::render(&self, area, buf);
+    }
+}
+
+impl Widget for &BonsaiWidget {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        let tree = self.grow(area.width, area.height);
+        self.draw(&tree, true, area, buf);
+    }
+}
+
+/// How far a [`BonsaiWidget`] has grown
+#[derive(Clone, Debug, Default)]
+pub struct BonsaiState {
+    tree: Vec<Val>,
+    /// The size of area the tree was grown for, `None` until it is rendered
+    size: Option<(u16, u16)>,
+    shown: usize,
+}
+
+impl BonsaiState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show one more step of growth
+    pub fn tick(&mut self) {
+        if !self.is_done() {
+            self.shown += 1;
+        }
+    }
+
+    /// Whether every step of the tree is shown. False until it has been
+    /// rendered once
+    pub fn is_done(&self) -> bool {
+        self.size.is_some() && self.shown >= self.tree.len()
+    }
+
+    /// Start growing from nothing again
+    pub fn restart(&mut self) {
+        self.shown = 0;
+    }
+}
+
+impl StatefulWidget for BonsaiWidget {
+    type State = BonsaiState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut BonsaiState) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &BonsaiWidget {
+    type State = BonsaiState;
+
+    /// Regrows the tree when the area changes size, keeping how many steps
+    /// are shown
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut BonsaiState) {
+        let area = area.intersection(buf.area);
+        let size = (area.width, area.height);
+        if state.size != Some(size) {
+            state.tree = self.grow(size.0, size.1);
+            state.size = Some(size);
+        }
+        state.shown = state.shown.min(state.tree.len());
+        self.draw(&state.tree[..state.shown], state.is_done(), area, buf);
+    }
+}
+
+/// Copy what was drawn on the grid into `area` of the buffer
+fn copy(grid: &Grid, area: Rect, buf: &mut Buffer) {
+    let blank = Cell::default();
+    for y in 0..grid.height() {
+        for (x, cell) in grid.row(y).iter().enumerate() {
+            if *cell == blank {
+                continue;
+            }
+            let target = buf.get_mut(area.x + x as u16, area.y + y);
+            if cell.symbol.is_empty() {
+                // covered by the wide character to the left, the same as
+                // ratatui leaves it
+                target.reset();
+            } else {
+                target
+                    .set_symbol(&cell.symbol)
+                    .set_style(style(&cell.style));
+            }
+        }
+    }
+}
+
+/// The ratatui style for a cell. The terminal's default colors become no
+/// color, so the background of the area shows through
+fn style(style: &Style) -> style::Style {
+    let mut converted = style::Style::default();
+    if let Some(fg) = color(style.foreground_color) {
+        converted = converted.fg(fg);
+    }
+    if let Some(bg) = color(style.background_color) {
+        converted = converted.bg(bg);
+    }
+    converted.add_modifier(modifier(style.attribute))
+}
+
+fn color(color: Color) -> Option<style::Color> {
+    let color = match color {
+        Color::Reset => return None,
+        Color::Black => style::Color::Black,
+        Color::DarkRed => style::Color::Red,
+        Color::DarkGreen => style::Color::Green,
+        Color::DarkYellow => style::Color::Yellow,
+        Color::DarkBlue => style::Color::Blue,
+        Color::DarkMagenta => style::Color::Magenta,
+        Color::DarkCyan => style::Color::Cyan,
+        Color::Grey => style::Color::Gray,
+        Color::DarkGrey => style::Color::DarkGray,
+        Color::Red => style::Color::LightRed,
+        Color::Green => style::Color::LightGreen,
+        Color::Yellow => style::Color::LightYellow,
+        Color::Blue => style::Color::LightBlue,
+        Color::Magenta => style::Color::LightMagenta,
+        Color::Cyan => style::Color::LightCyan,
+        Color::White => style::Color::White,
+        Color::Rgb { r, g, b } => style::Color::Rgb(r, g, b),
+        Color::AnsiValue(index) => style::Color::Indexed(index),
+    };
+    Some(color)
+}
+
+fn modifier(attribute: Attribute) -> Modifier {
+    match attribute {
+        Attribute::Bold => Modifier::BOLD,
+        Attribute::Dim => Modifier::DIM,
+        Attribute::Italic => Modifier::ITALIC,
+        Attribute::Underlined => Modifier::UNDERLINED,
+        Attribute::SlowBlink => Modifier::SLOW_BLINK,
+        Attribute::RapidBlink => Modifier::RAPID_BLINK,
+        Attribute::Reverse => Modifier::REVERSED,
+        Attribute::Hidden => Modifier::HIDDEN,
+        Attribute::CrossedOut => Modifier::CROSSED_OUT,
+        _ => Modifier::empty(),
+    }
+}
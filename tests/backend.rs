@@ -0,0 +1,153 @@
+//! Drawing onto a virtual screen must show what [`render`] lays out, since
+//! the terminal and exports are expected to look the same
+#![cfg(all(feature = "cli", feature = "crossterm"))]
+
+mod common;
+
+use std::io::Write;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{self, Colors, Print, SetAttribute, SetColors, SetForegroundColor},
+};
+use rbonsai::{
+    backend::{Backend, VirtualScreen, WriterBackend},
+    bonsai::{
+        draw_tree, grow_tree, init,
+        utility::{create_message_window, Input, Style},
+        Canvas,
+    },
+    render::{render, Grid},
+    rng::TreeRng,
+    save::SavedTree,
+    style::{Attribute, Color},
+    theme::ColorSupport,
+};
+
+/// Draw the scene for the given flags the way the terminal does, and the way
+/// exports do
+fn draw(args: &[&str], width: u16, height: u16) -> (VirtualScreen, Grid) {
+    let config = common::config(args);
+    let canvas = Canvas::new(width, height, config.base().as_ref());
+    let tree = grow_tree(&config, &mut TreeRng::new(1), canvas);
+
+    let mut screen = VirtualScreen::new(width, height);
+    init(&mut screen, &config).unwrap();
+    assert_eq!(draw_tree(&mut screen, &config, &tree).unwrap(), None);
+    if let Some(message) = &config.message {
+        create_message_window(&mut screen, message, config.color_support()).unwrap();
+    }
+
+    let expected = render(
+        &tree,
+        config.base_art().as_ref(),
+        config.message.as_deref(),
+        width,
+        height,
+    );
+    (screen, expected)
+}
+
+#[test]
+fn screen_matches_render() {
+    let (screen, expected) = draw(&["--color", "never", "-m", "hello"], 80, 24);
+    assert_eq!(screen.grid().to_plain_string(), expected.to_plain_string());
+}
+
+#[test]
+fn screen_keeps_colors() {
+    for args in [
+        ["--color", "always", "--theme", "autumn", "-m", "in color"],
+        ["--color", "always", "-b", "rock", "-m", "in color"],
+    ] {
+        let (screen, expected) = draw(&args, 70, 30);
+        // the terminal gets 24-bit colors only if COLORTERM says it can
+        // show them, so compare both with the palette
+        assert_eq!(
+            screen.grid().to_ansi_string(ColorSupport::Ansi256),
+            expected.to_ansi_string(ColorSupport::Ansi256)
+        );
+    }
+}
+
+#[test]
+fn messages_bigger_than_the_screen_are_clipped() {
+    let long = "a".repeat(66_000);
+    let words = "word ".repeat(20_000);
+    for message in [&long, &words] {
+        let (screen, expected) = draw(&["--color", "never", "-b", "none", "-m", message], 4, 20);
+        assert_eq!(screen.grid().to_plain_string(), expected.to_plain_string());
+    }
+}
+
+#[test]
+fn writes_can_be_split_anywhere() {
+    let mut whole = VirtualScreen::new(20, 5);
+    let mut split = VirtualScreen::new(20, 5);
+    let mut text = Vec::new();
+    queue!(
+        text,
+        MoveTo(3, 2),
+        SetAttribute(style::Attribute::Bold),
+        SetForegroundColor(style::Color::Rgb { r: 1, g: 2, b: 3 }),
+        Print("é木&"),
+        SetColors(Colors::new(style::Color::Reset, style::Color::Reset)),
+        MoveTo(0, 4),
+        Print("ok"),
+    )
+    .unwrap();
+
+    whole.write_all(&text).unwrap();
+    for byte in &text {
+        split.write_all(&[*byte]).unwrap();
+    }
+    assert_eq!(whole.grid(), split.grid());
+    assert_eq!(whole.grid().to_plain_string(), "\n\n   é木&\n\nok\n");
+    assert_eq!(
+        whole.grid().cell(3, 2).unwrap().style,
+        Style {
+            attribute: Attribute::Bold,
+            foreground_color: Color::Rgb { r: 1, g: 2, b: 3 },
+            background_color: Color::Reset,
+        }
+    );
+    assert_eq!(whole.cursor(), (2, 4));
+}
+
+#[test]
+fn input_stops_live_drawing() {
+    let config = common::config(&["--live", "-t", "0"]);
+    let tree = grow_tree(&config, &mut TreeRng::new(1), Canvas::new(80, 24, None));
+    let mut screen = VirtualScreen::new(80, 24);
+    screen.send_input(Input::Resize(100, 30));
+    let input = draw_tree(&mut screen, &config, &tree).unwrap();
+    assert_eq!(input, Some(Input::Resize(100, 30)));
+    assert_eq!(screen.wait_for_input(None).unwrap(), None);
+}
+
+#[test]
+fn loaded_trees_are_clipped_to_the_screen() {
+    let config = common::config(&["--color", "never", "-b", "none", "-L", "60"]);
+    let tree = grow_tree(&config, &mut TreeRng::new(3), Canvas::new(80, 24, None));
+    let mut saved = SavedTree::new(&config, 3, (80, 24), tree);
+    saved.fit_to((30, 10));
+
+    let mut screen = VirtualScreen::new(30, 10);
+    draw_tree(&mut screen, &config, &saved.tree).unwrap();
+    let expected = render(&saved.tree, None, None, 30, 10);
+    assert_eq!(screen.grid().to_plain_string(), expected.to_plain_string());
+}
+
+#[test]
+fn writer_backend_uses_its_size() {
+    let config = common::config(&["--color", "never", "-m", "hello"]);
+    let mut backend = WriterBackend::new(Vec::new(), 40, 12);
+    init(&mut backend, &config).unwrap();
+    create_message_window(&mut backend, "hello", config.color_support()).unwrap();
+
+    let mut screen = VirtualScreen::new(40, 12);
+    screen.write_all(&backend.into_inner()).unwrap();
+    let expected = render(&[], config.base_art().as_ref(), Some("hello"), 40, 12);
+    assert_eq!(screen.grid().to_plain_string(), expected.to_plain_string());
+}
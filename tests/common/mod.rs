@@ -0,0 +1,37 @@
+// each test only uses some of these
+#![allow(dead_code)]
+
+use std::{env, fs, path::PathBuf};
+
+#[cfg(feature = "cli")]
+use clap::Parser;
+#[cfg(feature = "cli")]
+use rbonsai::Config;
+
+/// The config for the given flags, without reading a config file
+#[cfg(feature = "cli")]
+pub fn config(args: &[&str]) -> Config {
+    Config::try_parse_from(["rbonsai"].iter().chain(args)).unwrap()
+}
+
+/// Compare `actual` with the snapshot checked in at `tests/<path>`. Set
+/// `RBONSAI_BLESS` to write the snapshot instead, after checking the change
+/// is intended
+pub fn assert_snapshot(path: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path);
+    if env::var_os("RBONSAI_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("{}: {}, bless it with RBONSAI_BLESS=1", path.display(), err));
+    assert!(
+        actual == expected,
+        "{} doesn't match, got:\n{}",
+        path.display(),
+        actual
+    );
+}
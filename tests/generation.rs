@@ -0,0 +1,119 @@
+//! Seeds must grow the same tree on every platform and release with the
+//! same `GENERATION_VERSION`. If these fail after an intended change to
+//! generation, bump the version and bless new goldens with
+//! `RBONSAI_BLESS=1 cargo test --test generation`
+
+mod common;
+
+use rbonsai::{
+    base::base_art,
+    lsystem::LSystem,
+    render::render,
+    rng::{parse_seed, seed_string, TreeRng, GENERATION_VERSION},
+    species::Species,
+    theme::BaseColors,
+    tree::{Tree, TreeBuilder},
+};
+
+fn check_golden(name: &str, tree: &Tree, base: &str) {
+    let art = base_art(base, &BaseColors::default());
+    let grid = render(
+        &tree.cells,
+        art.as_ref(),
+        None,
+        tree.canvas.width,
+        tree.canvas.height,
+    );
+    common::assert_snapshot(
+        &format!("golden/v{}/{}.txt", GENERATION_VERSION, name),
+        &grid.to_plain_string(),
+    );
+}
+
+#[test]
+fn splitmix64_reference_values() {
+    let mut rng = TreeRng::new(1234567);
+    let values: Vec<u64> = (0..5).map(|_| rng.next_u64()).collect();
+    assert_eq!(
+        values,
+        [
+            6457827717110365317,
+            3203168211198807973,
+            9817491932198370423,
+            4593380528125082431,
+            16408922859458223821,
+        ]
+    );
+}
+
+#[test]
+fn ranges_are_stable() {
+    let mut rng = TreeRng::new(42);
+    let values: Vec<i32> = (0..12).map(|_| rng.range(-3, 4)).collect();
+    assert_eq!(values, [0, -3, 3, 1, -2, 3, -1, 2, -3, 0, -1, 2]);
+    assert_eq!(rng.range(5, 5), 5);
+    assert_eq!(rng.range(i32::MIN, i32::MAX), 57535881);
+}
+
+#[test]
+fn seeds_name_their_generation() {
+    let seed = seed_string(42);
+    assert_eq!(seed, format!("{}:42", GENERATION_VERSION));
+    assert_eq!(parse_seed(&seed), Ok(42));
+    assert_eq!(parse_seed("42"), Ok(42));
+    assert!(parse_seed(&format!("{}:42", GENERATION_VERSION + 1)).is_err());
+}
+
+#[test]
+fn default_tree() {
+    let tree = TreeBuilder::new().seed(42).build().unwrap();
+    assert_eq!(tree.generation, GENERATION_VERSION);
+    check_golden("default_80x24", &tree, "large");
+}
+
+#[test]
+fn large_tree_with_small_base() {
+    let tree = TreeBuilder::new()
+        .seed(7)
+        .life(60)
+        .multiplier(5)
+        .leaves(["&", "*", "@"])
+        .base("small")
+        .canvas_size(120, 40)
+        .build()
+        .unwrap();
+    check_golden("life60_120x40", &tree, "small");
+}
+
+#[test]
+fn species() {
+    for name in [
+        "juniper",
+        "weeping-willow",
+        "pine",
+        "maple",
+        "cascade",
+        "broom",
+    ] {
+        let tree = TreeBuilder::new()
+            .seed(3)
+            .species(Species::builtin(name).unwrap())
+            .canvas_size(90, 30)
+            .build()
+            .unwrap();
+        check_golden(&format!("species_{}", name), &tree, "large");
+    }
+}
+
+#[test]
+fn lsystem() {
+    let grammar = "axiom = X\nangle = 25\niterations = 4\nX -> F+[[X]-X]-F[-FX]+X\nF -> FF\n";
+    let lsystem = LSystem::parse("plant.ls".as_ref(), grammar).unwrap();
+    let tree = TreeBuilder::new()
+        .seed(11)
+        .lsystem(lsystem)
+        .canvas_size(80, 30)
+        .build()
+        .unwrap();
+    check_golden("lsystem_plant", &tree, "large");
+}
@@ -0,0 +1,71 @@
+//! A saved tree must load back exactly as it was drawn, with the base and
+//! grammar it was grown with
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::{env, fs, path::PathBuf, process};
+
+use rbonsai::{
+    bonsai::{grow_tree, Canvas},
+    render::{render, Grid},
+    bonsai::Val,
+    rng::TreeRng,
+    save::{load_tree, save_tree, SavedTree},
+    theme::ColorSupport,
+    Config,
+};
+
+fn data(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn scene(config: &Config, tree: &[Val]) -> Grid {
+    render(tree, config.base_art().as_ref(), None, 50, 20)
+}
+
+/// Grow a tree with the given flags, save it and load it into a config with
+/// none of them
+fn round_trip(name: &str, args: &[&str]) {
+    let config = common::config(args);
+    let canvas = Canvas::new(50, 20, config.base().as_ref());
+    let tree = grow_tree(&config, &mut TreeRng::new(5), canvas);
+    let expected = scene(&config, &tree);
+
+    let path = env::temp_dir().join(format!("rbonsai-{}-{}.json", name, process::id()));
+    save_tree(&path, &SavedTree::new(&config, 5, (50, 20), tree)).unwrap();
+    let saved = load_tree(&path);
+    fs::remove_file(&path).unwrap();
+    let saved = saved.unwrap();
+
+    let mut loaded = common::config(&["--color", "never"]);
+    saved.apply_to(&mut loaded);
+    assert_eq!(loaded.base_file, config.base_file, "{}", name);
+    assert_eq!(loaded.lsystem, config.lsystem, "{}", name);
+    assert_eq!(
+        scene(&loaded, &saved.tree).to_ansi_string(ColorSupport::Ansi256),
+        expected.to_ansi_string(ColorSupport::Ansi256),
+        "{}",
+        name
+    );
+}
+
+#[test]
+fn saved_trees_load_the_same() {
+    round_trip("default", &[]);
+    round_trip("small", &["-b", "small", "--species", "pine", "-L", "20"]);
+}
+
+#[test]
+fn saved_trees_keep_base_files() {
+    round_trip("base-file", &["--base-file", &data("pot.toml")]);
+}
+
+#[test]
+fn saved_trees_keep_lsystems() {
+    round_trip("lsystem", &["--lsystem", &data("plant.ls"), "-b", "rock"]);
+}
@@ -0,0 +1,224 @@
+//! Snapshots of whole scenes as the command line draws them: the tree grown
+//! from the options, the base and the message box. After an intended change,
+//! check the new output and bless it with
+//! `RBONSAI_BLESS=1 cargo test --test snapshots`
+#![cfg(feature = "cli")]
+
+mod common;
+
+use rbonsai::{
+    base::{base_art, BUILTIN_BASES},
+    bonsai::{grow_tree, BranchType, Canvas, Val},
+    lsystem::LSystem,
+    render::{render, Grid},
+    rng::TreeRng,
+    species::{Species, BUILTIN_SPECIES},
+    theme::{BaseColors, ColorSupport},
+    Config, Error,
+};
+
+fn grow(config: &Config, width: u16, height: u16) -> (Vec<Val>, Canvas) {
+    let canvas = Canvas::new(width, height, config.base().as_ref());
+    let mut rng = TreeRng::new(config.seed.unwrap_or(0));
+    (grow_tree(config, &mut rng, canvas), canvas)
+}
+
+fn scene(args: &[&str], width: u16, height: u16) -> Grid {
+    let config = common::config(args);
+    let (tree, _) = grow(&config, width, height);
+    render(
+        &tree,
+        config.base_art().as_ref(),
+        config.message.as_deref(),
+        width,
+        height,
+    )
+}
+
+#[test]
+fn default_scene() {
+    let grid = scene(&["-s", "1"], 80, 24);
+    common::assert_snapshot("snapshots/default.txt", &grid.to_plain_string());
+}
+
+#[test]
+fn message_box() {
+    let grid = scene(&["-s", "2", "-m", "hello there"], 80, 24);
+    common::assert_snapshot("snapshots/message.txt", &grid.to_plain_string());
+}
+
+#[test]
+fn wrapped_message_box() {
+    let message =
+        "a tree grown in a terminal, with a message long enough to wrap over several lines";
+    let grid = scene(&["-s", "3", "-b", "small", "-m", message], 100, 30);
+    common::assert_snapshot("snapshots/message_wrapped.txt", &grid.to_plain_string());
+}
+
+#[test]
+fn every_base() {
+    for base in BUILTIN_BASES {
+        let grid = scene(&["-s", "4", "-b", base, "-c", "&,*"], 70, 25);
+        common::assert_snapshot(
+            &format!("snapshots/base_{}.txt", base),
+            &grid.to_plain_string(),
+        );
+    }
+}
+
+#[test]
+fn big_tree() {
+    let grid = scene(&["-s", "5", "-L", "60", "-M", "6"], 140, 45);
+    common::assert_snapshot("snapshots/big.txt", &grid.to_plain_string());
+}
+
+#[test]
+fn colors() {
+    let grid = scene(&["-s", "6", "--theme", "autumn", "-m", "autumn"], 60, 20);
+    common::assert_snapshot(
+        "snapshots/autumn_256.ansi",
+        &grid.to_ansi_string(ColorSupport::Ansi256),
+    );
+    common::assert_snapshot(
+        "snapshots/autumn_truecolor.ansi",
+        &grid.to_ansi_string(ColorSupport::TrueColor),
+    );
+}
+
+/// Every cell of the tree is on the canvas and above the base
+fn assert_in_bounds(tree: &[Val], canvas: Canvas, case: &str) {
+    let max_y = canvas.ground() as i32;
+    for val in tree {
+        assert!(
+            val.pos.x >= 0 && val.pos.x < canvas.width as i32,
+            "{}: {:?} is off the side of the canvas",
+            case,
+            val.pos
+        );
+        assert!(
+            val.pos.y >= 0 && val.pos.y < canvas.height as i32,
+            "{}: {:?} is off the top or bottom of the canvas",
+            case,
+            val.pos
+        );
+        assert!(
+            val.pos.y <= max_y,
+            "{}: {:?} is under the base",
+            case,
+            val.pos
+        );
+    }
+}
+
+#[test]
+fn trees_stay_on_the_canvas() {
+    let sizes = [(80, 24), (120, 40), (40, 12), (20, 8), (7, 5)];
+    for species in BUILTIN_SPECIES {
+        for base in BUILTIN_BASES {
+            for (width, height) in sizes {
+                for seed in 0..25 {
+                    let seed = seed.to_string();
+                    let config = common::config(&["-s", &seed, "-b", base, "--species", species]);
+                    let (tree, canvas) = grow(&config, width, height);
+                    let case = format!("{} {} {}x{} seed {}", species, base, width, height, seed);
+                    assert_in_bounds(&tree, canvas, &case);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn lsystems_stay_on_the_canvas() {
+    let grammars = [
+        "axiom = X\nangle = 25\niterations = 5\nX -> F+[[X]-X]-F[-FX]+X\nF -> FF",
+        "axiom = F\nangle = 90\niterations = 3\nF -> F[+F]F[-F]F",
+        // spirals down below where it starts
+        "axiom = F\nangle = 100\niterations = 3\nF -> F+F-F+F|F",
+    ];
+    for grammar in grammars {
+        let lsystem = LSystem::parse("test.ls".as_ref(), grammar).unwrap();
+        for base in BUILTIN_BASES {
+            for (width, height) in [(80, 24), (30, 10), (3, 3)] {
+                let mut config = common::config(&["-s", "1", "-b", base]);
+                config.lsystem = Some(lsystem.clone());
+                let (tree, canvas) = grow(&config, width, height);
+                let case = format!("{:?} {} {}x{}", grammar, base, width, height);
+                assert_in_bounds(&tree, canvas, &case);
+            }
+        }
+    }
+}
+
+#[test]
+fn lsystem_trunks_start_on_the_ground() {
+    // leaves and pops before the first line don't lift the trunk
+    for axiom in ["LF", "]F", "L]LF"] {
+        let grammar = format!("axiom = {}\nangle = 25\niterations = 0", axiom);
+        let mut config = common::config(&["-s", "1", "-b", "none"]);
+        config.lsystem = Some(LSystem::parse("test.ls".as_ref(), &grammar).unwrap());
+        let (tree, canvas) = grow(&config, 40, 20);
+        let ground = canvas.ground() as i32;
+        assert!(
+            tree.iter().any(|val| {
+                val.branch_type == BranchType::Trunk && val.pos.x == 20 && val.pos.y == ground
+            }),
+            "{:?} has no trunk on the ground",
+            axiom
+        );
+    }
+}
+
+#[test]
+fn lsystems_count_symbols_not_bytes() {
+    // 2^17 two-byte symbols are under the limit in symbols but over it in bytes
+    let grammar = "axiom = é\nangle = 25\niterations = 17\né -> éé";
+    let lsystem = LSystem::parse("test.ls".as_ref(), grammar).unwrap();
+    assert_eq!(lsystem.expand().chars().count(), 1 << 17);
+}
+
+#[test]
+fn tiny_canvases_render() {
+    for width in 0..8 {
+        for height in 0..8 {
+            scene(&["-s", "1", "-m", "a message"], width, height);
+            scene(&["-s", "1", "-b", "none"], width, height);
+        }
+    }
+}
+
+#[test]
+fn species_bring_leaves_and_colors() {
+    let pine = Species::builtin("pine").unwrap();
+    let config = common::config(&["--species", "pine"]);
+    assert_eq!(config.leaves(), pine.leaves.clone().unwrap());
+    assert_eq!(config.theme().tree, pine.colors.unwrap());
+
+    // unless they are given
+    let config = common::config(&["--species", "pine", "-c", "*", "--theme", "autumn"]);
+    assert_eq!(config.leaves(), ["*"]);
+    assert_eq!(config.theme(), common::config(&["--theme", "autumn"]).theme());
+}
+
+#[test]
+fn bases_fit_at_their_minimum_width() {
+    for name in BUILTIN_BASES {
+        let Some(art) = base_art(name, &BaseColors::default()) else {
+            continue;
+        };
+        let height = art.lines.len() as u16;
+        let Err(Error::TerminalTooSmall { min_width, .. }) = art.check_fits(0, height) else {
+            panic!("{} fits on nothing", name);
+        };
+        art.check_fits(min_width, height).unwrap();
+
+        // every column of the art is drawn
+        let grid = render(&[], Some(&art), None, min_width, height);
+        let (x, _) = art.origin(min_width, height);
+        for (line, row) in art.lines.iter().zip(grid.to_plain_string().lines()) {
+            let text: String = line.iter().map(|span| span.text.as_str()).collect();
+            let expected = format!("{}{}", " ".repeat(x as usize), text);
+            assert_eq!(row, expected.trim_end(), "{} at {} wide", name, min_width);
+        }
+    }
+}
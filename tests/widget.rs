@@ -0,0 +1,149 @@
+//! The widget must show the same scene as [`render`], in whatever part of
+//! the buffer it is given
+#![cfg(feature = "ratatui")]
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{StatefulWidget, Widget},
+};
+use rbonsai::{
+    base::base_art,
+    render::{render, Grid},
+    theme::BaseColors,
+    tree::TreeBuilder,
+    widget::{BonsaiState, BonsaiWidget},
+};
+
+/// The text of `area` of the buffer, formatted like `Grid::to_plain_string`
+fn plain(buf: &Buffer, area: Rect) -> String {
+    let mut out = String::new();
+    for y in area.top()..area.bottom() {
+        let mut line = String::new();
+        let mut skip = 0;
+        for x in area.left()..area.right() {
+            // the cells after a wide character are blank in the buffer but
+            // left out of the grid
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            let symbol = buf.get(x, y).symbol();
+            skip = unicode_width::UnicodeWidthStr::width(symbol).saturating_sub(1);
+            line.push_str(symbol);
+        }
+        out.push_str(line.trim_end_matches(' '));
+        out.push('\n');
+    }
+    out
+}
+
+fn expected(builder: TreeBuilder, steps: Option<usize>, message: Option<&str>) -> Grid {
+    let tree = builder.build().unwrap();
+    let cells = &tree.cells[..steps.unwrap_or(tree.cells.len())];
+    let art = base_art("large", &BaseColors::default());
+    render(
+        cells,
+        art.as_ref(),
+        message,
+        tree.canvas.width,
+        tree.canvas.height,
+    )
+}
+
+#[test]
+fn widget_matches_render() {
+    let builder = TreeBuilder::new().seed(7);
+    let area = Rect::new(0, 0, 80, 24);
+    let mut buf = Buffer::empty(area);
+    let widget = BonsaiWidget::new(builder.clone()).message("hello");
+    Widget::render(widget, area, &mut buf);
+    assert_eq!(
+        plain(&buf, area),
+        expected(builder, None, Some("hello")).to_plain_string()
+    );
+}
+
+#[test]
+fn widget_stays_in_its_area() {
+    let builder = TreeBuilder::new().seed(7);
+    let mut buf = Buffer::empty(Rect::new(0, 0, 100, 40));
+    buf.set_style(buf.area, Style::new().bg(Color::Blue));
+    let area = Rect::new(10, 5, 60, 30);
+    let widget = BonsaiWidget::new(builder.clone()).scale_life(false);
+    Widget::render(widget, area, &mut buf);
+
+    let builder = builder.canvas_size(60, 30);
+    assert_eq!(
+        plain(&buf, area),
+        expected(builder, None, None).to_plain_string()
+    );
+    for y in 0..40 {
+        for x in 0..100 {
+            let cell = buf.get(x, y);
+            if !area.contains((x, y).into()) {
+                assert_eq!(cell.symbol(), " ");
+            }
+            // the tree and base have no background of their own
+            assert_eq!(cell.bg, Color::Blue, "at {}, {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn life_scales_with_the_area() {
+    let builder = TreeBuilder::new().seed(3).life(32);
+    let area = Rect::new(0, 0, 160, 48);
+    let mut buf = Buffer::empty(area);
+    Widget::render(BonsaiWidget::new(builder.clone()), area, &mut buf);
+    assert_eq!(
+        plain(&buf, area),
+        expected(builder.life(64).canvas_size(160, 48), None, None).to_plain_string()
+    );
+}
+
+#[test]
+fn state_grows_one_step_per_tick() {
+    let builder = TreeBuilder::new().seed(9);
+    let widget = BonsaiWidget::new(builder.clone()).message("done");
+    let area = Rect::new(0, 0, 80, 24);
+    let mut state = BonsaiState::new();
+    assert!(!state.is_done());
+
+    for step in 0..5 {
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(&widget, area, &mut buf, &mut state);
+        assert_eq!(
+            plain(&buf, area),
+            expected(builder.clone(), Some(step), None).to_plain_string()
+        );
+        state.tick();
+    }
+
+    while !state.is_done() {
+        state.tick();
+    }
+    let mut buf = Buffer::empty(area);
+    StatefulWidget::render(&widget, area, &mut buf, &mut state);
+    assert_eq!(
+        plain(&buf, area),
+        expected(builder, None, Some("done")).to_plain_string()
+    );
+
+    state.restart();
+    assert!(!state.is_done());
+}
+
+#[test]
+fn unseeded_widget_keeps_its_tree() {
+    let widget = BonsaiWidget::new(TreeBuilder::new());
+    let area = Rect::new(0, 0, 80, 24);
+    let mut first = Buffer::empty(area);
+    Widget::render(&widget, area, &mut first);
+    // trees without a seed are seeded by the second
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let mut second = Buffer::empty(area);
+    Widget::render(&widget, area, &mut second);
+    assert_eq!(plain(&first, area), plain(&second, area));
+}